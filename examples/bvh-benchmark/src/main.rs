@@ -0,0 +1,161 @@
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use min_rt::base::color::Color;
+use min_rt::canvas::u8_canvas::U8Canvas;
+use min_rt::cgmath::Vector3;
+use min_rt::scene::bvh::Bvh;
+use min_rt::scene::renderer;
+use min_rt::scene::scene::{Attenuation, Light, Plane, Scene, Specs, Sphere};
+use min_rt::scene::shape::{Hit, Material, Ray, Shape};
+
+const OBJECT_COUNTS: [usize; 5] = [10, 100, 1_000, 10_000, 100_000];
+const RAY_COUNT: usize = 20_000;
+const CANVAS_SIZE: usize = 200;
+
+fn main() {
+
+    let mut random = Random::new(12345);
+
+    println!("{} rays per query test, {}x{} canvas per render", RAY_COUNT, CANVAS_SIZE, CANVAS_SIZE);
+    println!();
+    println!("{:>8} | {:>10} | {:>12} | {:>12} | {:>8} | {:>12}",
+             "objects", "bvh build", "linear query", "bvh query", "speedup", "render (bvh)");
+
+    for count in OBJECT_COUNTS {
+
+        let scene = make_scene(count, &mut random);
+
+        // Random rays starting from around the camera, heading into the field of spheres
+        let rays: Vec<Ray> = (0..RAY_COUNT)
+            .map(|_| {
+                let direction = Vector3::new(random.range(-1.0, 1.0), random.range(-1.0, 1.0), 1.0);
+                Ray::new(scene.specs.camera_pos, direction)
+            })
+            .collect();
+
+        let start = Instant::now();
+        let bvh = Bvh::from_shapes(&scene.objects);
+        let build_duration = start.elapsed();
+
+        // Skip the linear test for the largest scenes, where it takes forever
+        let linear_duration = if count <= 10_000 {
+            let start = Instant::now();
+            let mut hits = 0;
+            for ray in &rays {
+                if closest_hit_linear(&scene.objects, ray).is_some() {
+                    hits += 1;
+                }
+            }
+            std::hint::black_box(hits);
+            Some(start.elapsed())
+        } else {
+            None
+        };
+
+        let start = Instant::now();
+        let mut hits = 0;
+        for ray in &rays {
+            let hit = bvh.closest_hit(ray, 0.0, f64::INFINITY, |i, t_max| {
+                scene.objects[i].intersect(ray, 0.0, t_max)
+            });
+            if hit.is_some() {
+                hits += 1;
+            }
+        }
+        std::hint::black_box(hits);
+        let bvh_duration = start.elapsed();
+
+        let scene = Arc::new(RwLock::new(scene));
+        let mut canvas = U8Canvas::new(CANVAS_SIZE, CANVAS_SIZE);
+        let start = Instant::now();
+        renderer::render_to_canvas_all_mt(&scene, &mut canvas, num_cpus::get());
+        let render_duration = start.elapsed();
+
+        let (linear_text, speedup_text) = match linear_duration {
+            Some(duration) => (
+                format_duration(duration),
+                format!("{:.1}x", duration.as_secs_f64() / bvh_duration.as_secs_f64())),
+            None => ("-".to_string(), "-".to_string())
+        };
+        println!("{:>8} | {:>10} | {:>12} | {:>12} | {:>8} | {:>12}",
+                 count,
+                 format_duration(build_duration),
+                 linear_text,
+                 format_duration(bvh_duration),
+                 speedup_text,
+                 format_duration(render_duration));
+    }
+}
+
+/// Makes a scene with `count` small spheres randomly placed in a cube in front of the camera
+fn make_scene(count: usize, random: &mut Random) -> Scene {
+
+    let mut specs = Specs::new_with_defaults();
+    specs.camera_pos = Vector3::new(0.0, 0.0, -60.0);
+
+    let white = Color::new(1.0, 1.0, 1.0);
+    let lights = vec![
+        Light::Ambient { intensity: 0.2, color: white },
+        Light::Point {
+            intensity: 0.6,
+            color: white,
+            position: Vector3::new(20.0, 40.0, -40.0),
+            attenuation: Attenuation::None,
+            range: None,
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+        },
+        Light::Directional { intensity: 0.2, color: white, direction: Vector3::new(1.0, 4.0, -4.0) },
+    ];
+
+    let mut objects = Vec::<Box<dyn Shape>>::with_capacity(count + 1);
+    let radius = 25.0 / (count as f64).cbrt();
+    for _ in 0..count {
+        let center = Vector3::new(
+            random.range(-25.0, 25.0), random.range(-25.0, 25.0), random.range(-25.0, 25.0));
+        let color = Color::new(random.range(0.2, 1.0), random.range(0.2, 1.0), random.range(0.2, 1.0));
+        let material = Material { color: color.into(), specular: 100.0, reflective: 0.2, transparency: 0.0, ior: 1.0 };
+        objects.push(Box::new(Sphere { center, radius: radius * random.range(0.3, 0.6), material }));
+    }
+    let material = Material {
+        color: Color::new(0.5, 0.5, 0.5).into(), specular: 0.0, reflective: 0.0, transparency: 0.0, ior: 1.0 };
+    objects.push(Box::new(Plane {
+        point: Vector3::new(0.0, -30.0, 0.0), normal: Vector3::new(0.0, 1.0, 0.0), material }));
+
+    Scene { specs, lights, objects }
+}
+
+/// The brute force approach which the bvh replaces
+fn closest_hit_linear(objects: &[Box<dyn Shape>], ray: &Ray) -> Option<(Hit, usize)> {
+    let mut result: Option<(Hit, usize)> = None;
+    let mut closest_t = f64::INFINITY;
+    for (i, object) in objects.iter().enumerate() {
+        if let Some(hit) = object.intersect(ray, 0.0, closest_t) {
+            closest_t = hit.t;
+            result = Some((hit, i));
+        }
+    }
+    result
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.2}ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// Minimal deterministic pseudo-random number generator (xorshift), so results are repeatable
+struct Random {
+    state: u64,
+}
+
+impl Random {
+    fn new(seed: u64) -> Random {
+        Random { state: seed.max(1) }
+    }
+
+    fn range(&mut self, min: f64, max: f64) -> f64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        let unit = (self.state >> 11) as f64 / (1_u64 << 53) as f64;
+        min + (max - min) * unit
+    }
+}
@@ -4,6 +4,7 @@ use min_rt::base::color::Color;
 use min_rt::util::ansi;
 use min_rt::scene::renderer;
 use min_rt::scene::scene::{Light, Scene, Specs, Sphere};
+use min_rt::scene::shape::{Material, Shape};
 use min_rt::canvas::console_canvas_multi::ConsoleCanvasMulti;
 
 fn main() {
@@ -14,21 +15,21 @@ fn main() {
         specs.pixel_ar = 0.40; // tall pixel aspect ratio because terminal
         specs.background_color.set(0.0, 0.0, 0.0);
 
-        let light = Light::Ambient { intensity: 1.0 };
-        let mut lights = Vec::<Light>::new();
-        lights.push(light);
+        let light = Light::Ambient { intensity: 1.0, color: Color::new(1.0, 1.0, 1.0) };
+        let lights = vec![light];
 
         let sphere = Sphere {
             center: Vector3::<f64>::from([0.0, 0.0, 3.0]),
             radius: 1.0,
-            color: Color::from_u8(255, 0, 0),
-            specular: 500.0,
-            reflective: 0.0,
-            transparency: 0.0 };
-        let mut spheres = Vec::<Sphere>::new();
-        spheres.push(sphere);
-
-        Scene { specs, lights, spheres }
+            material: Material {
+                color: Color::from_u8(255, 0, 0).into(),
+                specular: 500.0,
+                reflective: 0.0,
+                transparency: 0.0,
+                ior: 1.0 } };
+        let objects: Vec<Box<dyn Shape>> = vec![Box::new(sphere)];
+
+        Scene { specs, lights, objects }
     };
 
     // Note the extra necessary step of wrapping the scene with Arc<RwLock>>
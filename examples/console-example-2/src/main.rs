@@ -8,8 +8,9 @@ use min_rt::{scene, util};
 use min_rt::quaternion_from_euler;
 use min_rt::canvas::console_canvas_multi::ConsoleCanvasMulti;
 use min_rt::cgmath::{Euler, InnerSpace};
+use min_rt::scene::camera::{Stereo, StereoLayout};
 use min_rt::scene::renderer;
-use min_rt::scene::scene::{Light, Scene};
+use min_rt::scene::scene::{Light, Scene, Sphere};
 use min_rt::util::ansi;
 
 const MS_PER_FRAME: i64 = 33;
@@ -22,7 +23,8 @@ fn main() {
 
     // Load scene using yaml config file
     let path = util::file::find_file_starting_from_cwd("scene1.yaml").unwrap();
-    let mut scene = scene::loader::load(&path).expect("Error in scene file, aborting");
+    let mut scene = scene::loader::load(&path)
+        .unwrap_or_else(|e| panic!("Error in scene file, aborting: {}", e));
     // Adjust pixel aspect ratio because terminal
     scene.specs.pixel_ar = 0.40;
 
@@ -41,6 +43,7 @@ fn main() {
 
     // Start the render loop
     let mut time = 0_f64;
+    let mut keys = Vec::<Keycode>::new();
     loop {
         let start = Instant::now();
 
@@ -62,11 +65,22 @@ fn main() {
         canvas.clear_chars(' ');
         canvas.set_text(2, 2, "hello min-rt");
         canvas.set_text(2, 3, &format!("time {}", time.floor()));
+        let stereo = scene.read().unwrap().specs.stereo.map(|stereo| stereo.layout);
+        canvas.set_text(2, 4, &format!("stereo {:?} (S)", stereo));
 
         // Print to the console
         canvas.print_to_console();
 
-        if device_state.get_keys().contains(&Keycode::Escape) {
+        // Keys which were pressed since the last frame
+        let previous_keys = keys;
+        keys = device_state.get_keys();
+        let is_pressed = |key: Keycode| keys.contains(&key) && !previous_keys.contains(&key);
+
+        if is_pressed(Keycode::S) {
+            cycle_stereo(&mut scene);
+        }
+
+        if keys.contains(&Keycode::Escape) {
             // Restore terminal state somewhat, and quit
             print!("{}{}{}",
                    ansi::CODE_SHOW_CURSOR,
@@ -84,6 +98,19 @@ fn main() {
     }
 }
 
+/// Cycles from mono through each of the stereo layouts
+fn cycle_stereo(scene: &mut Arc<RwLock<Scene>>) {
+    let mut scene = scene.write().unwrap();
+    let layout = match scene.specs.stereo.map(|stereo| stereo.layout) {
+        None => Some(StereoLayout::SideBySide),
+        Some(StereoLayout::SideBySide) => Some(StereoLayout::TopBottom),
+        Some(StereoLayout::TopBottom) => Some(StereoLayout::Anaglyph),
+        Some(StereoLayout::Anaglyph) => None,
+    };
+    // The objects are about 5 units from the camera
+    scene.specs.stereo = layout.map(|layout| Stereo { layout, eye_separation: 0.15, convergence: 5.0 });
+}
+
 /// Adds some rudimentary movement for fun
 fn update_scene(scene: &mut Arc<RwLock<Scene>>, time: f64) {
 
@@ -91,11 +118,12 @@ fn update_scene(scene: &mut Arc<RwLock<Scene>>, time: f64) {
     let mut scene = scene.write().unwrap();
 
     // sphere position
-    let mut pos = &mut scene.spheres[0].center;
-    pos.y = (time * 1.25).to_radians().sin() * 1.5;
+    let sphere = scene.get_object_mut::<Sphere>(0).unwrap();
+    sphere.center.y = (time * 1.25).to_radians().sin() * 1.5;
 
     // sphere transparency
-    scene.spheres[1].transparency = (time * 3.0).to_radians().sin() * 0.3 + 0.7;
+    let sphere = scene.get_object_mut::<Sphere>(1).unwrap();
+    sphere.material.transparency = (time * 3.0).to_radians().sin() * 0.3 + 0.7;
 
     // camera position and orientation
     let radians = (time * 0.5).to_radians();
@@ -108,7 +136,7 @@ fn update_scene(scene: &mut Arc<RwLock<Scene>>, time: f64) {
 
     // light
     let light: &mut Light = &mut scene.lights[1];
-    if let Light::Point { intensity: _, position } = light {
+    if let Light::Point { position, .. } = light {
         position.x = x;
         position.z = z;
     }
@@ -120,8 +148,5 @@ fn make_canvas_using_term_size() -> ConsoleCanvasMulti {
 }
 
 fn get_terminal_size() -> (usize, usize) {
-    match term_size::dimensions() {
-        Some(size) => size,
-        None => (80, 60) // fallback
-    }
+    term_size::dimensions().unwrap_or((80, 60)) // fallback
 }
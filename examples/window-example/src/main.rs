@@ -7,10 +7,13 @@ use winit::event_loop::{ControlFlow, EventLoop};
 use winit::window::{WindowBuilder};
 use winit_input_helper::WinitInputHelper;
 use min_rt::canvas::u8_canvas::U8Canvas;
+use min_rt::canvas::image_file;
 use min_rt::{quaternion_from_euler, scene, util};
-use min_rt::cgmath::{Euler, InnerSpace};
+use min_rt::cgmath::{Euler, InnerSpace, Vector3};
+use min_rt::scene::camera::{Stereo, StereoLayout};
+use min_rt::scene::moving::Moving;
 use min_rt::scene::renderer;
-use min_rt::scene::scene::{Light, Scene};
+use min_rt::scene::scene::{Light, SamplePattern, Scene, Sphere};
 
 const WIDTH: usize = 800;
 const HEIGHT: usize = 800;
@@ -26,7 +29,12 @@ fn main() -> Result<(), Error> {
     }
 
     let path = util::file::find_file_starting_from_cwd("scene1.yaml").unwrap();
-    let scene = scene::loader::load(&path).expect("Error in scene file, aborting");
+    let mut scene = scene::loader::load(&path)
+        .unwrap_or_else(|e| panic!("Error in scene file, aborting: {}", e));
+
+    // Wrap the bouncing sphere so that it can be given a velocity, for motion blur
+    let sphere = scene.objects.remove(0);
+    scene.objects.insert(0, Box::new(Moving::new(sphere, Vector3::new(0.0, 0.0, 0.0))));
 
     // Note the extra necessary step of wrapping the scene with Arc<RwLock>>
     // for multi-threading purposes
@@ -80,7 +88,7 @@ fn main() -> Result<(), Error> {
                 }
             }
             Event::WindowEvent {
-                event: WindowEvent::CursorMoved {device_id,position, .. }, ..
+                event: WindowEvent::CursorMoved { position, .. }, ..
             } => {
                 let logical_position = position.to_logical::<i32>(window.scale_factor());
                 // println!("Scale Factor: {}    Physical {:?}    Logical: {:?}", window.scale_factor(), position, logical_position);
@@ -110,6 +118,24 @@ fn main() -> Result<(), Error> {
                 is_scene_dirty = true;
             }
 
+            if input.key_pressed(VirtualKeyCode::B) {
+                toggle_motion_blur(&mut scene);
+                is_scene_dirty = true;
+            }
+
+            if input.key_pressed(VirtualKeyCode::S) {
+                cycle_stereo(&mut scene);
+                is_scene_dirty = true;
+            }
+
+            if input.key_pressed(VirtualKeyCode::P) {
+                // Save the current frame
+                match image_file::write_png(&canvas, "render.png") {
+                    Ok(_) => println!("Saved render.png"),
+                    Err(e) => println!("Could not save render.png: {}", e)
+                }
+            }
+
             if is_scene_dirty || should_update_every_frame {
 
                 update_scene(&mut scene, time);
@@ -128,34 +154,82 @@ fn main() -> Result<(), Error> {
     });
 }
 
+/// Motion blur needs several samples per pixel to look smooth, so is slower
+fn toggle_motion_blur(scene: &mut Arc<RwLock<Scene>>) {
+    let mut scene = scene.write().unwrap();
+    let specs = &mut scene.specs;
+    if specs.shutter_close > specs.shutter_open {
+        specs.shutter_close = specs.shutter_open;
+        specs.samples_per_pixel = 1;
+        specs.sample_pattern = SamplePattern::Grid;
+        println!("Motion blur off");
+    } else {
+        // Shutter open for half of each frame
+        specs.shutter_open = 0.0;
+        specs.shutter_close = 0.5;
+        specs.samples_per_pixel = 9;
+        specs.sample_pattern = SamplePattern::Jittered;
+        println!("Motion blur on");
+    }
+}
+
+/// Cycles from mono through each of the stereo layouts
+fn cycle_stereo(scene: &mut Arc<RwLock<Scene>>) {
+    let mut scene = scene.write().unwrap();
+    let layout = match scene.specs.stereo.map(|stereo| stereo.layout) {
+        None => Some(StereoLayout::SideBySide),
+        Some(StereoLayout::SideBySide) => Some(StereoLayout::TopBottom),
+        Some(StereoLayout::TopBottom) => Some(StereoLayout::Anaglyph),
+        Some(StereoLayout::Anaglyph) => None,
+    };
+    // The objects are about 5 units from the camera
+    scene.specs.stereo = layout.map(|layout| Stereo { layout, eye_separation: 0.15, convergence: 5.0 });
+    println!("Stereo: {:?}", layout);
+}
+
 fn update_scene(scene: &mut Arc<RwLock<Scene>>, time: f64) {
 
     // Write-lock and unwrap to gain access to the (mutatable) scene data.
     let mut scene = scene.write().unwrap();
 
+    // Positions are functions of time, so that velocities (ie, the change over one frame)
+    // can be given for motion blur
+    let next_time = time + TIME_INCREMENT;
+
     // sphere position
-    let mut pos = &mut scene.spheres[0].center;
-    pos.y = (time * 1.25).to_radians().sin() * 1.5;
+    let sphere_y = |time: f64| (time * 1.25).to_radians().sin() * 1.5;
+    let moving = scene.get_object_mut::<Moving>(0).unwrap();
+    moving.velocity.y = sphere_y(next_time) - sphere_y(time);
+    let sphere = moving.get_shape_mut::<Sphere>().unwrap();
+    sphere.center.y = sphere_y(time);
 
     // sphere transparency
-    scene.spheres[1].transparency = (time * 2.0).to_radians().cos() * 0.5 + 0.5; // * 0.3 + 0.3;
+    let sphere = scene.get_object_mut::<Sphere>(1).unwrap();
+    sphere.material.transparency = (time * 2.0).to_radians().cos() * 0.5 + 0.5; // * 0.3 + 0.3;
 
     // camera position and orientation
+    let camera_pos = |time: f64| {
+        let radians = (time * 0.5).to_radians();
+        Vector3::new(radians.sin() * 5.0, 0.0, 3.0 + (radians.cos() * -5.0))
+    };
+    let pos = camera_pos(time);
+    scene.specs.camera_pos.x = pos.x;
+    scene.specs.camera_pos.z = pos.z;
+    scene.specs.camera_velocity = camera_pos(next_time) - pos;
     let radians = (time * 0.5).to_radians();
-    let x = 0.0 + (radians.sin() * 5.0);
-    let z = 3.0 + (radians.cos() * -5.0);
-    scene.specs.camera_pos.x = x;
-    scene.specs.camera_pos.z = z;
     let euler = Euler::<f64>::new(0.0, radians * -0.5, 0.0);
     scene.specs.camera_orientation = quaternion_from_euler(euler).normalize();
 
     // light
-    let light: &mut Light = &mut scene.lights[1];
-    if let Light::Point { intensity: _, position } = light {
+    let light_pos = |time: f64| {
         let radians = (time * 0.8).to_radians();
-        let x = 0.0 + (radians.sin() * 5.0);
-        let z = 3.0 + (radians.cos() * -5.0);
-        position.x = x;
-        position.z = z;
+        Vector3::new(radians.sin() * 5.0, 0.0, 3.0 + (radians.cos() * -5.0))
+    };
+    let light: &mut Light = &mut scene.lights[1];
+    if let Light::Point { position, velocity, .. } = light {
+        let pos = light_pos(time);
+        position.x = pos.x;
+        position.z = pos.z;
+        *velocity = light_pos(next_time) - pos;
     }
 }
@@ -2,10 +2,15 @@ use std::ops;
 use crate::util::maths;
 
 /// Library's color class
-/// Values are expected to be between 0.0 and 1.0
-/// Any math operations get clamped to that range. // todo revisit; need guards on setters etc?
+/// Displayable values are between 0.0 and 1.0.
+/// Math operations are not clamped, so that a color can also represent radiance
+/// (eg, a bright highlight may exceed 1.0). See `ToneMapping` for bringing such values
+/// back into the displayable range; `to_u8` simply clamps.
 ///
-#[derive(Clone, Copy, Debug)]
+/// Lighting math expects linear values. Colors from outside sources (eg, color pickers,
+/// image files) are usually sRGB-encoded, and should be converted using `from_srgb_u8`.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Color {
     pub r: f64,
     pub g: f64,
@@ -21,6 +26,7 @@ impl Color {
         Color::new(0.0, 0.0, 0.0)
     }
 
+    /// Straight scaling to 0.0-1.0, with no change of encoding
     pub fn from_u8(r: u8, g: u8, b: u8) -> Color {
         Color {
             r: (r as f64) / 255.0,
@@ -29,6 +35,11 @@ impl Color {
         }
     }
 
+    /// Makes a linear color from sRGB-encoded values
+    pub fn from_srgb_u8(r: u8, g: u8, b: u8) -> Color {
+        Color::from_u8(r, g, b).srgb_to_linear()
+    }
+
     pub fn set(&mut self, r: f64, g: f64, b: f64) { // todo clamp to legal range?
         self.r = r;
         self.g = g;
@@ -41,37 +52,84 @@ impl Color {
         self.b = color.b;
     }
 
+    /// Straight scaling to 0-255 (rounded), with no change of encoding
     pub fn to_u8(&self) -> (u8, u8, u8) {
-        let r = (self.r * 255.0) as u8;  // todo verify off-by-1 related
-        let g = (self.g * 255.0) as u8;
-        let b = (self.b * 255.0) as u8;
+        let c = self.clamped();
+        let r = (c.r * 255.0).round() as u8;
+        let g = (c.g * 255.0).round() as u8;
+        let b = (c.b * 255.0).round() as u8;
         (r, g, b)
     }
 
+    /// Encodes the linear color as sRGB values
+    pub fn to_srgb_u8(&self) -> (u8, u8, u8) {
+        self.linear_to_srgb().to_u8()
+    }
+
+    /// Applies the sRGB transfer function (ie, gamma-encodes the color)
+    pub fn linear_to_srgb(&self) -> Color {
+        fn encode(c: f64) -> f64 {
+            if c <= 0.0031308 { 12.92 * c } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+        }
+        Color::new(encode(self.r), encode(self.g), encode(self.b))
+    }
+
+    /// Inverse of `linear_to_srgb`
+    pub fn srgb_to_linear(&self) -> Color {
+        fn decode(c: f64) -> f64 {
+            if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+        }
+        Color::new(decode(self.r), decode(self.g), decode(self.b))
+    }
+
+    /// Relative luminance (Rec. 709 weights), treating the values as linear
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    /// Returns the color with each component clamped to the displayable range
+    pub fn clamped(&self) -> Color {
+        Color::new(maths::clamp(self.r, 0.0, 1.0), maths::clamp(self.g, 0.0, 1.0), maths::clamp(self.b, 0.0, 1.0))
+    }
+
     pub fn lerp(a: Color, b: Color, value: f64) -> Color {
         a * (1.0 - value)  +  b * value
     }
 }
 
+/// How linear color values get encoded when rendering for display
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputTransfer {
+    /// No encoding (ie, the values are written as-is)
+    Linear,
+    /// The sRGB transfer function, as expected by most displays and image files
+    Srgb,
+}
+
+impl OutputTransfer {
+    pub fn encode(&self, color: Color) -> Color {
+        match self {
+            OutputTransfer::Linear => color,
+            OutputTransfer::Srgb => color.linear_to_srgb(),
+        }
+    }
+}
+
 // operator overloads
 
 // Color Color
 impl ops::Add<Color> for Color {
     type Output = Color;
     fn add(self, rhs: Color) -> Color {
-        let mut r = self.r + rhs.r;
-        if r > 1.0 {
-            r = 1.0;
-        }
-        let mut g = self.g + rhs.g;
-        if g > 1.0 {
-            g = 1.0;
-        }
-        let mut b = self.b + rhs.b;
-        if b > 1.0 {
-            b = 1.0;
-        }
-        Color::new(r, g, b)
+        Color::new(self.r + rhs.r, self.g + rhs.g, self.b + rhs.b)
+    }
+}
+
+// Color Color (component-wise, eg for filtering light through a surface color)
+impl ops::Mul<Color> for Color {
+    type Output = Color;
+    fn mul(self, rhs: Color) -> Color {
+        Color::new(self.r * rhs.r, self.g * rhs.g, self.b * rhs.b)
     }
 }
 
@@ -79,19 +137,7 @@ impl ops::Add<Color> for Color {
 impl ops::Mul<f64> for Color {
     type Output = Color;
     fn mul(self, rhs: f64) -> Color {
-        let mut r = self.r * rhs;
-        if r > 1.0 {
-            r = 1.0;
-        }
-        let mut g = self.g * rhs;
-        if g > 1.0 {
-            g = 1.0;
-        }
-        let mut b = self.b * rhs;
-        if b > 1.0 {
-            b = 1.0;
-        }
-        Color::new(r, g, b)
+        Color::new(self.r * rhs, self.g * rhs, self.b * rhs)
     }
 }
 
@@ -99,19 +145,6 @@ impl ops::Mul<f64> for Color {
 impl ops::Mul<f64> for &Color {
     type Output = Color;
     fn mul(self, rhs: f64) -> Color {
-        let r = self.r * rhs;
-        let mut r = maths::clamp(r, 0.0, 1.0);
-        if r > 1.0 {
-            r = 1.0;
-        }
-        let mut g = self.g * rhs;
-        if g > 1.0 {
-            g = 1.0;
-        }
-        let mut b = self.b * rhs;
-        if b > 1.0 {
-            b = 1.0;
-        }
-        Color::new(r, g, b)
+        *self * rhs
     }
-}
\ No newline at end of file
+}
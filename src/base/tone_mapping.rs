@@ -0,0 +1,100 @@
+use crate::base::color::Color;
+
+/// Operator for mapping unbounded radiance values into the displayable range (0.0 to 1.0)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ToneMapping {
+    /// Clips values above 1.0. Bright areas saturate towards white, and lose their hue.
+    Clamp,
+    /// Reinhard's operator, applied to luminance so that hue is preserved: `L / (1 + L)`
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve, applied per channel
+    AcesFilmic,
+}
+
+impl ToneMapping {
+
+    /// Maps `color` to the displayable range, after scaling it by `exposure` (in stops)
+    pub fn apply(&self, color: Color, exposure: f64) -> Color {
+        let color = color * exposure.exp2();
+        match self {
+            ToneMapping::Clamp => color.clamped(),
+            ToneMapping::Reinhard => {
+                let luminance = color.luminance();
+                if luminance <= 0.0 {
+                    return Color::new_black();
+                }
+                (color * (1.0 / (1.0 + luminance))).clamped()
+            },
+            ToneMapping::AcesFilmic => {
+                Color::new(aces(color.r), aces(color.g), aces(color.b)).clamped()
+            }
+        }
+    }
+}
+
+fn aces(x: f64) -> f64 {
+    let x = x.max(0.0);
+    (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOLERANCE: f64 = 1e-9;
+
+    fn gray(value: f64) -> Color {
+        Color::new(value, value, value)
+    }
+
+    fn assert_gray(color: Color, expected: f64) {
+        for component in [color.r, color.g, color.b] {
+            assert!((component - expected).abs() < TOLERANCE, "expected {}, got {:?}", expected, color);
+        }
+    }
+
+    #[test]
+    fn clamp() {
+        assert_gray(ToneMapping::Clamp.apply(gray(0.0), 0.0), 0.0);
+        assert_gray(ToneMapping::Clamp.apply(gray(1.0), 0.0), 1.0);
+        assert_gray(ToneMapping::Clamp.apply(gray(1000.0), 0.0), 1.0);
+        assert_gray(ToneMapping::Clamp.apply(gray(0.3), 0.0), 0.3);
+    }
+
+    #[test]
+    fn reinhard() {
+        assert_gray(ToneMapping::Reinhard.apply(gray(0.0), 0.0), 0.0);
+        assert_gray(ToneMapping::Reinhard.apply(gray(1.0), 0.0), 0.5);
+        assert_gray(ToneMapping::Reinhard.apply(gray(1000.0), 0.0), 1000.0 / 1001.0);
+    }
+
+    #[test]
+    fn reinhard_preserves_hue() {
+        let color = ToneMapping::Reinhard.apply(Color::new(2.0, 1.0, 0.0), 0.0);
+        assert!((color.r / color.g - 2.0).abs() < TOLERANCE);
+        assert_eq!(color.b, 0.0);
+    }
+
+    #[test]
+    fn aces() {
+        assert_gray(ToneMapping::AcesFilmic.apply(gray(0.0), 0.0), 0.0);
+        assert_gray(ToneMapping::AcesFilmic.apply(gray(1.0), 0.0), 2.54 / 3.16);
+        // The curve's asymptote is above 1.0, so very large values get clamped
+        assert_gray(ToneMapping::AcesFilmic.apply(gray(1000.0), 0.0), 1.0);
+    }
+
+    #[test]
+    fn exposure_scales_by_powers_of_two() {
+        assert_gray(ToneMapping::Clamp.apply(gray(0.25), 1.0), 0.5);
+        assert_gray(ToneMapping::Clamp.apply(gray(0.25), 2.0), 1.0);
+        assert_gray(ToneMapping::Clamp.apply(gray(0.25), -1.0), 0.125);
+        for tone_mapping in [ToneMapping::Clamp, ToneMapping::Reinhard, ToneMapping::AcesFilmic] {
+            let color = Color::new(0.3, 0.6, 0.9);
+            let expected = tone_mapping.apply(color * 8.0, 0.0);
+            let actual = tone_mapping.apply(color, 3.0);
+            assert!((expected.r - actual.r).abs() < TOLERANCE);
+            assert!((expected.g - actual.g).abs() < TOLERANCE);
+            assert!((expected.b - actual.b).abs() < TOLERANCE);
+        }
+    }
+}
@@ -76,7 +76,7 @@ impl ConsoleCanvasMulti {
                 row_text += &code;
                 // Add the literal character
                 let char = self.chars_canvas.get_value(ix, iy);
-                row_text.push(char.clone());
+                row_text.push(char);
             }
 
             // print the row
@@ -0,0 +1,61 @@
+use crate::canvas::canvas::Canvas;
+use crate::base::color::{Color, OutputTransfer};
+use crate::base::tone_mapping::ToneMapping;
+
+///
+/// Color canvas which holds unclamped values (ie, radiance, before tone mapping)
+///
+pub struct FloatCanvas {
+    width: usize,
+    height: usize,
+    pub data: Vec<Color>
+}
+
+impl Canvas<Color> for FloatCanvas {
+
+    fn get_width(&self) -> usize {
+        self.width
+    }
+
+    fn get_height(&self) -> usize {
+        self.height
+    }
+
+    fn get_value(&self, x: usize, y: usize) -> Color {
+        self.data[self.get_flat_index(x, y)]
+    }
+
+    fn set_value(&mut self, x: usize, y: usize, color: &Color) {
+        let i = self.get_flat_index(x, y);
+        self.data[i] = *color;
+    }
+}
+
+impl FloatCanvas {
+
+    pub fn new(width: usize, height: usize) -> Self {
+        let data = vec![Color::new_black(); width * height];
+        FloatCanvas { width, height, data }
+    }
+
+    /// Writes the values into `target` (eg, a `U8Canvas` or console canvas), tone mapped and encoded.
+    /// `target` must have the same dimensions.
+    pub fn tone_map_to(
+            &self,
+            target: &mut dyn Canvas<Color>,
+            tone_mapping: ToneMapping,
+            exposure: f64,
+            output_transfer: OutputTransfer) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let color = tone_mapping.apply(self.get_value(x, y), exposure);
+                target.set_value(x, y, &output_transfer.encode(color));
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn get_flat_index(&self, x: usize, y: usize) -> usize {
+        (y * self.width) + x
+    }
+}
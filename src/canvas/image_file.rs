@@ -0,0 +1,277 @@
+//! Writers for saving a canvas to an image file, in binary PPM or PNG format.
+//!
+//! Both are implemented here without external dependencies. The PNG encoder uses a simple
+//! deflate implementation (greedy LZ77 matching plus the fixed Huffman codes), which compresses
+//! reasonably well for rendered images without being especially fast or optimal.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::base::color::Color;
+use crate::canvas::canvas::Canvas;
+
+/// Writes the canvas to a file, using the format implied by the file extension (`.png` or `.ppm`)
+pub fn write(canvas: &dyn Canvas<Color>, filepath: &str) -> io::Result<()> {
+    let extension = Path::new(filepath)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase());
+    match extension.as_deref() {
+        Some("png") => write_png(canvas, filepath),
+        Some("ppm") => write_ppm(canvas, filepath),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput, format!("unsupported image file extension: {}", filepath)))
+    }
+}
+
+pub fn write_ppm(canvas: &dyn Canvas<Color>, filepath: &str) -> io::Result<()> {
+    fs::write(filepath, encode_ppm(canvas))
+}
+
+pub fn write_png(canvas: &dyn Canvas<Color>, filepath: &str) -> io::Result<()> {
+    fs::write(filepath, encode_png(canvas))
+}
+
+/// Returns the canvas as a binary PPM ("P6") file
+pub fn encode_ppm(canvas: &dyn Canvas<Color>) -> Vec<u8> {
+    let mut result = format!("P6\n{} {}\n255\n", canvas.get_width(), canvas.get_height()).into_bytes();
+    result.extend(get_rgb_rows(canvas).concat());
+    result
+}
+
+/// Returns the canvas as an 8-bit RGB PNG file
+pub fn encode_png(canvas: &dyn Canvas<Color>) -> Vec<u8> {
+
+    // Image data is each row prefixed by its filter type.
+    // Uses the 'sub' filter throughout (ie, each byte stored as the difference from the pixel to its left).
+    let mut filtered = Vec::<u8>::new();
+    for row in get_rgb_rows(canvas) {
+        filtered.push(1);
+        for i in 0..row.len() {
+            let left = if i >= 3 { row[i - 3] } else { 0 };
+            filtered.push(row[i].wrapping_sub(left));
+        }
+    }
+
+    let mut header = Vec::<u8>::new();
+    header.extend((canvas.get_width() as u32).to_be_bytes());
+    header.extend((canvas.get_height() as u32).to_be_bytes());
+    header.extend([8, 2, 0, 0, 0]); // bit depth, color type (rgb), compression, filter, interlace
+
+    let mut result = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+    write_png_chunk(&mut result, b"IHDR", &header);
+    write_png_chunk(&mut result, b"IDAT", &zlib_compress(&filtered));
+    write_png_chunk(&mut result, b"IEND", &[]);
+    result
+}
+
+fn get_rgb_rows(canvas: &dyn Canvas<Color>) -> Vec<Vec<u8>> {
+    let mut rows = Vec::with_capacity(canvas.get_height());
+    for y in 0..canvas.get_height() {
+        let mut row = Vec::with_capacity(canvas.get_width() * 3);
+        for x in 0..canvas.get_width() {
+            let (r, g, b) = canvas.get_value(x, y).to_u8();
+            row.extend([r, g, b]);
+        }
+        rows.push(row);
+    }
+    rows
+}
+
+fn write_png_chunk(output: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    output.extend((data.len() as u32).to_be_bytes());
+    let start = output.len();
+    output.extend(chunk_type);
+    output.extend(data);
+    let crc = crc32(&output[start..]);
+    output.extend(crc.to_be_bytes());
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffff_u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { 0xedb8_8320 ^ (crc >> 1) } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let mut a = 1_u32;
+    let mut b = 0_u32;
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+// ---
+// Deflate
+
+const WINDOW_SIZE: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const HASH_SIZE: usize = 1 << 15;
+
+const LENGTH_BASE: [usize; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31,
+    35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA_BITS: [u32; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASE: [usize; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193,
+    257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DISTANCE_EXTRA_BITS: [u32; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+/// Writes values to a byte vector least-significant bit first, as deflate requires
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    fn new() -> BitWriter {
+        BitWriter { bytes: Vec::new(), buffer: 0, count: 0 }
+    }
+
+    fn write_bits(&mut self, value: u32, count: u32) {
+        self.buffer |= (value as u64) << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    /// Huffman codes are defined most-significant bit first, so get written reversed
+    fn write_code(&mut self, code: u32, count: u32) {
+        self.write_bits(code.reverse_bits() >> (32 - count), count);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+/// Wraps deflated data in the zlib format
+fn zlib_compress(data: &[u8]) -> Vec<u8> {
+    let mut result = vec![0x78, 0x01]; // deflate with 32k window, no preset dictionary
+    result.extend(deflate(data));
+    result.extend(adler32(data).to_be_bytes());
+    result
+}
+
+/// Compresses the data as a single deflate block using the fixed Huffman codes
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+    writer.write_bits(1, 1); // final block
+    writer.write_bits(1, 2); // fixed Huffman codes
+
+    // Most recent position of each hashed 3-byte sequence, plus the chain of earlier positions
+    let mut head = vec![usize::MAX; HASH_SIZE];
+    let mut previous = vec![usize::MAX; data.len()];
+    let hash = |i: usize| -> usize {
+        let value = (data[i] as usize) << 16 | (data[i + 1] as usize) << 8 | data[i + 2] as usize;
+        (value.wrapping_mul(2654435761) >> 8) & (HASH_SIZE - 1)
+    };
+
+    let mut i = 0;
+    while i < data.len() {
+        let mut best_length = 0;
+        let mut best_distance = 0;
+
+        if i + MIN_MATCH <= data.len() {
+            let h = hash(i);
+            let max_length = MAX_MATCH.min(data.len() - i);
+            let mut candidate = head[h];
+            let mut chain = 0;
+            while candidate != usize::MAX && i - candidate <= WINDOW_SIZE && chain < 32 {
+                let mut length = 0;
+                while length < max_length && data[candidate + length] == data[i + length] {
+                    length += 1;
+                }
+                if length > best_length {
+                    best_length = length;
+                    best_distance = i - candidate;
+                    if length == max_length {
+                        break;
+                    }
+                }
+                candidate = previous[candidate];
+                chain += 1;
+            }
+        }
+
+        let advance = if best_length >= MIN_MATCH {
+            write_length(&mut writer, best_length);
+            write_distance(&mut writer, best_distance);
+            best_length
+        } else {
+            write_literal(&mut writer, data[i] as u32);
+            1
+        };
+
+        // Record the positions being skipped over (which have enough bytes remaining to be hashed)
+        let end = (i + advance).min((data.len() + 1).saturating_sub(MIN_MATCH));
+        for (j, previous) in previous.iter_mut().enumerate().take(end).skip(i) {
+            let h = hash(j);
+            *previous = head[h];
+            head[h] = j;
+        }
+        i += advance;
+    }
+
+    write_literal(&mut writer, 256); // end of block
+    writer.finish()
+}
+
+/// Writes a literal/length symbol using the fixed Huffman code
+fn write_literal(writer: &mut BitWriter, symbol: u32) {
+    match symbol {
+        0..=143 => writer.write_code(0x30 + symbol, 8),
+        144..=255 => writer.write_code(0x190 + symbol - 144, 9),
+        256..=279 => writer.write_code(symbol - 256, 7),
+        _ => writer.write_code(0xc0 + symbol - 280, 8),
+    }
+}
+
+fn write_length(writer: &mut BitWriter, length: usize) {
+    let index = LENGTH_BASE.iter().rposition(|base| *base <= length).unwrap();
+    write_literal(writer, 257 + index as u32);
+    writer.write_bits((length - LENGTH_BASE[index]) as u32, LENGTH_EXTRA_BITS[index]);
+}
+
+fn write_distance(writer: &mut BitWriter, distance: usize) {
+    let index = DISTANCE_BASE.iter().rposition(|base| *base <= distance).unwrap();
+    writer.write_code(index as u32, 5);
+    writer.write_bits((distance - DISTANCE_BASE[index]) as u32, DISTANCE_EXTRA_BITS[index]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_known_values() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+    }
+
+    #[test]
+    fn adler32_known_values() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        // Long enough for the sums to wrap around the modulus
+        assert_eq!(adler32(&[0xff; 10000]), 0xb623_eb2b);
+    }
+}
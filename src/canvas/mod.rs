@@ -1,4 +1,7 @@
+#[allow(clippy::module_inception)]
 pub mod canvas;
 pub mod vector_canvas;
 pub mod console_canvas_multi;
 pub mod u8_canvas;
+pub mod float_canvas;
+pub mod image_file;
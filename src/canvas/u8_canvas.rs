@@ -28,7 +28,7 @@ impl Canvas<Color> for U8Canvas {
     fn set_value(&mut self, x: usize, y: usize, color: &Color) {
         let i = self.get_flat_index(x, y);
         let (r, g, b) = color.to_u8();
-        self.data[i + 0] = r;
+        self.data[i] = r;
         self.data[i + 1] = g;
         self.data[i + 2] = b;
         self.data[i + 3] = 255;
@@ -10,12 +10,13 @@ pub use cgmath;
 //      "the trait `Angle` is not implemented for `f64`"
 use crate::cgmath::{Euler, Quaternion};
 pub fn quaternion_from_euler(src: Euler<f64>) -> Quaternion<f64> {
-    let s_x = src.x.sin() * 0.5;
-    let c_x = src.x.cos() * 0.5;
-    let s_y = src.y.sin() * 0.5;
-    let c_y = src.y.cos() * 0.5;
-    let s_z = src.z.sin() * 0.5;
-    let c_z = src.z.cos() * 0.5;
+    // Quaternions use half the rotation angle
+    let s_x = (src.x * 0.5).sin();
+    let c_x = (src.x * 0.5).cos();
+    let s_y = (src.y * 0.5).sin();
+    let c_y = (src.y * 0.5).cos();
+    let s_z = (src.z * 0.5).sin();
+    let c_z = (src.z * 0.5).cos();
     Quaternion::<f64>::new(
         -s_x * s_y * s_z + c_x * c_y * c_z,
         s_x * c_y * c_z + s_y * s_z * c_x,
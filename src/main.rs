@@ -0,0 +1,160 @@
+//! Command-line renderer.
+//!
+//! Renders a YAML scene file to an image file (PNG or PPM, based on the file extension).
+
+use std::process::ExitCode;
+use std::sync::{Arc, RwLock};
+use std::thread;
+use min_rt::base::tone_mapping::ToneMapping;
+use min_rt::canvas::image_file;
+use min_rt::canvas::u8_canvas::U8Canvas;
+use min_rt::scene::{loader, renderer};
+
+const USAGE: &str = "\
+Usage: min-rt <scene.yaml> <output.png|output.ppm> [options]
+
+Options:
+  --width <n>     Image width in pixels (default 800)
+  --height <n>    Image height in pixels (default 800)
+  --threads <n>   Number of render threads (default: number of cpus)
+  --samples <n>   Samples per pixel, overriding the scene file's value
+  --light-samples <n>
+                  Shadow rays per area light, overriding the scene file's value
+  --tone-mapping <clamp|reinhard|aces>
+                  Tone mapping operator, overriding the scene file's value
+  --exposure <stops>
+                  Exposure adjustment, overriding the scene file's value
+  --strict        Fail on invalid lights and objects, rather than skipping them";
+
+struct Options {
+    scene_path: String,
+    output_path: String,
+    width: usize,
+    height: usize,
+    threads: usize,
+    samples: Option<usize>,
+    light_samples: Option<usize>,
+    tone_mapping: Option<ToneMapping>,
+    exposure: Option<f64>,
+    strict: bool,
+}
+
+fn main() -> ExitCode {
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let result = if options.strict {
+        loader::load_strict(&options.scene_path)
+    } else {
+        loader::load(&options.scene_path)
+    };
+    let mut scene = match result {
+        Ok(scene) => scene,
+        Err(e) => {
+            eprintln!("error: could not load scene file '{}'\n{}", options.scene_path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Some(samples) = options.samples {
+        scene.specs.samples_per_pixel = samples;
+    }
+    if let Some(light_samples) = options.light_samples {
+        scene.specs.light_samples = light_samples;
+    }
+    if let Some(tone_mapping) = options.tone_mapping {
+        scene.specs.tone_mapping = tone_mapping;
+    }
+    if let Some(exposure) = options.exposure {
+        scene.specs.exposure = exposure;
+    }
+    let scene = Arc::new(RwLock::new(scene));
+
+    let mut canvas = U8Canvas::new(options.width, options.height);
+    renderer::render_to_canvas_all_mt(&scene, &mut canvas, options.threads);
+
+    if let Err(e) = image_file::write(&canvas, &options.output_path) {
+        eprintln!("error: could not write image file '{}': {}", options.output_path, e);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut positionals = Vec::<String>::new();
+    let mut width = 800;
+    let mut height = 800;
+    let mut threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let mut samples = None;
+    let mut light_samples = None;
+    let mut tone_mapping = None;
+    let mut exposure = None;
+    let mut strict = false;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if !arg.starts_with("--") {
+            positionals.push(arg.clone());
+            continue;
+        }
+        match arg.as_str() {
+            "--strict" => strict = true,
+            "--tone-mapping" => {
+                let value = next_value(&mut iter, arg)?;
+                tone_mapping = Some(match value.as_str() {
+                    "clamp" => ToneMapping::Clamp,
+                    "reinhard" => ToneMapping::Reinhard,
+                    "aces" => ToneMapping::AcesFilmic,
+                    _ => return Err(format!("{} must be one of clamp, reinhard, aces, got '{}'", arg, value))
+                });
+            },
+            "--exposure" => {
+                let value = next_value(&mut iter, arg)?;
+                let stops = value.parse::<f64>()
+                    .ok()
+                    .filter(|stops| stops.is_finite())
+                    .ok_or(format!("{} must be a number, got '{}'", arg, value))?;
+                exposure = Some(stops);
+            },
+            "--width" => width = parse_positive(arg, next_value(&mut iter, arg)?)?,
+            "--height" => height = parse_positive(arg, next_value(&mut iter, arg)?)?,
+            "--threads" => threads = parse_positive(arg, next_value(&mut iter, arg)?)?,
+            "--samples" => samples = Some(parse_positive(arg, next_value(&mut iter, arg)?)?),
+            "--light-samples" => light_samples = Some(parse_positive(arg, next_value(&mut iter, arg)?)?),
+            _ => return Err(format!("unknown option {}", arg))
+        }
+    }
+
+    if positionals.len() != 2 {
+        return Err("expected a scene file and an output file".to_string());
+    }
+    let output_path = positionals.pop().unwrap();
+    let extension = output_path.rsplit('.').next().unwrap_or("").to_lowercase();
+    if extension != "png" && extension != "ppm" {
+        return Err(format!("output file must end in .png or .ppm, got '{}'", output_path));
+    }
+    let scene_path = positionals.pop().unwrap();
+    Ok(Options { scene_path, output_path, width, height, threads, samples, light_samples, tone_mapping, exposure, strict })
+}
+
+/// Returns the value following option `arg`
+fn next_value<'a>(iter: &mut impl Iterator<Item = &'a String>, arg: &str) -> Result<&'a String, String> {
+    iter.next().ok_or(format!("missing value for {}", arg))
+}
+
+fn parse_positive(arg: &str, value: &str) -> Result<usize, String> {
+    value.parse::<usize>()
+        .ok()
+        .filter(|value| *value > 0)
+        .ok_or(format!("{} must be a positive integer, got '{}'", arg, value))
+}
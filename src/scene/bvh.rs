@@ -0,0 +1,190 @@
+use crate::cgmath::Vector3;
+use crate::scene::shape::{Aabb, Hit, Ray, Shape};
+
+/// Max number of items held by a leaf node
+const LEAF_SIZE: usize = 4;
+
+/// Bounding volume hierarchy, for finding ray intersections without testing every item.
+///
+/// Built from a list of bounding boxes, and refers to the items by their index in that list,
+/// so it can be used for any collection of things (eg, a scene's objects, a mesh's triangles).
+/// Must be rebuilt whenever the items move.
+#[derive(Clone, Debug)]
+pub struct Bvh {
+    nodes: Vec<Node>,
+    /// Item indices, ordered such that each leaf node refers to a contiguous range
+    indices: Vec<usize>,
+    /// Items with infinite bounds (eg, planes), which always get tested
+    unbounded: Vec<usize>,
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    bounds: Aabb,
+    kind: NodeKind,
+}
+
+#[derive(Clone, Debug)]
+enum NodeKind {
+    Leaf { start: usize, count: usize },
+    /// Index of the right child. The left child always immediately follows its parent.
+    Interior { right: usize },
+}
+
+impl Bvh {
+    pub fn new(bounds: &[Aabb]) -> Bvh {
+        let mut indices = Vec::<usize>::new();
+        let mut unbounded = Vec::<usize>::new();
+        for (i, aabb) in bounds.iter().enumerate() {
+            if aabb.is_finite() {
+                indices.push(i);
+            } else {
+                unbounded.push(i);
+            }
+        }
+        let mut bvh = Bvh { nodes: Vec::new(), indices, unbounded };
+        if !bvh.indices.is_empty() {
+            let count = bvh.indices.len();
+            bvh.build(bounds, 0, count);
+        }
+        bvh
+    }
+
+    /// Convenience constructor, using the bounds of each shape
+    pub fn from_shapes(shapes: &[Box<dyn Shape>]) -> Bvh {
+        let bounds: Vec<Aabb> = shapes.iter().map(|shape| shape.bounds()).collect();
+        Bvh::new(&bounds)
+    }
+
+    /// Recursively makes the node for the items `indices[start..start + count]`,
+    /// splitting them at the median of their centroids along the longest axis.
+    fn build(&mut self, bounds: &[Aabb], start: usize, count: usize) {
+        let items = &mut self.indices[start..(start + count)];
+        let mut node_bounds = bounds[items[0]];
+        let mut centroid_bounds = Aabb::from_points(&[centroid(&bounds[items[0]])]);
+        for &i in items.iter() {
+            node_bounds = node_bounds.union(&bounds[i]);
+            centroid_bounds = centroid_bounds.union(&Aabb::from_points(&[centroid(&bounds[i])]));
+        }
+
+        if count <= LEAF_SIZE {
+            self.nodes.push(Node { bounds: node_bounds, kind: NodeKind::Leaf { start, count } });
+            return;
+        }
+
+        let extent = centroid_bounds.max - centroid_bounds.min;
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+        let half = count / 2;
+        items.select_nth_unstable_by(half, |&a, &b| {
+            centroid(&bounds[a])[axis].total_cmp(&centroid(&bounds[b])[axis])
+        });
+
+        let node_index = self.nodes.len();
+        self.nodes.push(Node { bounds: node_bounds, kind: NodeKind::Interior { right: 0 } });
+        self.build(bounds, start, half);
+        let right = self.nodes.len();
+        self.build(bounds, start + half, count - half);
+        self.nodes[node_index].kind = NodeKind::Interior { right };
+    }
+
+    /// Returns the closest intersection, and the index of the item intersected.
+    ///
+    /// `intersect` is called with an item index and the current max distance,
+    /// and should return the item's intersection within `t_min` and that distance, if any.
+    pub fn closest_hit<F>(&self, ray: &Ray, t_min: f64, t_max: f64, mut intersect: F) -> Option<(Hit, usize)>
+        where F: FnMut(usize, f64) -> Option<Hit> {
+
+        let mut result: Option<(Hit, usize)> = None;
+        let mut closest_t = t_max;
+        self.traverse(ray, t_min, &mut closest_t, &mut |i, closest_t| {
+            if let Some(hit) = intersect(i, *closest_t) {
+                *closest_t = hit.t;
+                result = Some((hit, i));
+            }
+            false
+        });
+        result
+    }
+
+    /// Returns the first intersection found (not necessarily the closest one),
+    /// and the index of the item intersected. Useful for shadow rays.
+    pub fn any_hit<F>(&self, ray: &Ray, t_min: f64, t_max: f64, mut intersect: F) -> Option<(Hit, usize)>
+        where F: FnMut(usize, f64) -> Option<Hit> {
+
+        let mut result: Option<(Hit, usize)> = None;
+        let mut t_max = t_max;
+        self.traverse(ray, t_min, &mut t_max, &mut |i, t_max| {
+            if let Some(hit) = intersect(i, *t_max) {
+                result = Some((hit, i));
+                return true;
+            }
+            false
+        });
+        result
+    }
+
+    /// Calls `visit` for every item whose bounds the ray passes through, within `t_min` and `t_max`.
+    /// `visit` may narrow `t_max`, and returns true to stop the traversal.
+    fn traverse<F>(&self, ray: &Ray, t_min: f64, t_max: &mut f64, visit: &mut F)
+        where F: FnMut(usize, &mut f64) -> bool {
+
+        for &i in &self.unbounded {
+            if visit(i, t_max) {
+                return;
+            }
+        }
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let inverse_direction = Vector3::new(
+            1.0 / ray.direction.x, 1.0 / ray.direction.y, 1.0 / ray.direction.z);
+        let mut stack = vec![0_usize];
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if !intersects_box(ray, inverse_direction, &node.bounds, t_min, *t_max) {
+                continue;
+            }
+            match node.kind {
+                NodeKind::Leaf { start, count } => {
+                    for &i in &self.indices[start..(start + count)] {
+                        if visit(i, t_max) {
+                            return;
+                        }
+                    }
+                },
+                NodeKind::Interior { right } => {
+                    stack.push(right);
+                    stack.push(node_index + 1);
+                }
+            }
+        }
+    }
+}
+
+fn centroid(aabb: &Aabb) -> Vector3<f64> {
+    (aabb.min + aabb.max) * 0.5
+}
+
+/// Slab test
+fn intersects_box(ray: &Ray, inverse_direction: Vector3<f64>, aabb: &Aabb, t_min: f64, t_max: f64) -> bool {
+    let mut t_min = t_min;
+    let mut t_max = t_max;
+    for axis in 0..3 {
+        let t1 = (aabb.min[axis] - ray.origin[axis]) * inverse_direction[axis];
+        let t2 = (aabb.max[axis] - ray.origin[axis]) * inverse_direction[axis];
+        // `min`/`max` ignore the NaN produced when the ray lies exactly on a slab boundary
+        t_min = t_min.max(t1.min(t2));
+        t_max = t_max.min(t1.max(t2));
+        if t_max < t_min {
+            return false;
+        }
+    }
+    true
+}
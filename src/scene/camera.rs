@@ -0,0 +1,130 @@
+use crate::cgmath::{InnerSpace, Matrix3, Quaternion, Vector3};
+
+/// Camera defined by its position, the point it's looking at, and its vertical field of view.
+///
+/// When set on `Specs`, takes the place of `camera_pos`, `camera_orientation`,
+/// and the viewport and canvas dimensions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    pub position: Vector3<f64>,
+    pub look_at: Vector3<f64>,
+    /// Which way is up. Needn't be perpendicular to the view direction, but mustn't be parallel to it.
+    pub up: Vector3<f64>,
+    /// Vertical field of view, in degrees
+    pub fov: f64,
+    /// Radius of the lens, for depth of field. 0.0 is a pinhole (ie, everything is in focus).
+    pub aperture: f64,
+    /// Distance from the camera to the plane which is in perfect focus
+    pub focus_distance: f64,
+}
+
+impl Camera {
+    /// Makes a pinhole camera, focused at `look_at`
+    pub fn new(position: Vector3<f64>, look_at: Vector3<f64>, up: Vector3<f64>, fov: f64) -> Camera {
+        let focus_distance = (look_at - position).magnitude();
+        Camera { position, look_at, up, fov, aperture: 0.0, focus_distance }
+    }
+
+    /// Returns the camera's orthonormal basis (right, up, forward)
+    pub fn basis(&self) -> (Vector3<f64>, Vector3<f64>, Vector3<f64>) {
+        let forward = (self.look_at - self.position).normalize();
+        let right = self.up.cross(forward).normalize();
+        let up = forward.cross(right);
+        (right, up, forward)
+    }
+
+    /// Returns the rotation from camera space (+x right, +y up, +z forward) to world space,
+    /// in the same form as `Specs::camera_orientation`
+    pub fn orientation(&self) -> Quaternion<f64> {
+        let (right, up, forward) = self.basis();
+        Quaternion::from(Matrix3::from_cols(right, up, forward)).normalize()
+    }
+}
+
+/// How primary rays are cast from the camera
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    /// Rays diverge from the camera's position
+    Perspective,
+    /// Parallel rays, cast from a view plane centered on the camera's position,
+    /// which is `height` world units tall (its width follows from the canvas's aspect ratio).
+    /// Useful for technical diagrams and isometric views.
+    Orthographic { height: f64 },
+    /// Full 360° panorama, mapping the canvas's x to longitude and y to latitude around the camera,
+    /// with the camera's forward direction at the center. Canvas would normally be twice as wide as it is tall.
+    Equirectangular,
+    /// Circular image filling the canvas's shorter side, with `fov` degrees across the circle
+    /// (up to 360). Equidistant, ie, distance from the center is proportional to the angle from forward.
+    Fisheye { fov: f64 },
+}
+
+/// Renders the view from two eyes, either side of the camera, into the one canvas
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stereo {
+    pub layout: StereoLayout,
+    /// Distance between the eyes, in world units
+    pub eye_separation: f64,
+    /// Distance from the camera at which the eyes' views line up (ie, objects at this distance
+    /// appear at the depth of the screen, nearer ones in front of it, and farther ones behind it)
+    pub convergence: f64,
+}
+
+/// How the two eyes' views are arranged on the canvas
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StereoLayout {
+    /// Left eye on the left half of the canvas, right eye on the right half
+    SideBySide,
+    /// Left eye on the top half of the canvas, right eye on the bottom half
+    TopBottom,
+    /// Both eyes over the full canvas, for red/cyan glasses: the red channel comes from the left eye,
+    /// and green and blue from the right
+    Anaglyph,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cgmath::Rotation;
+
+    const TOLERANCE: f64 = 1e-12;
+
+    fn assert_vec_eq(actual: Vector3<f64>, expected: Vector3<f64>) {
+        assert!((actual - expected).magnitude() < TOLERANCE, "expected {:?}, got {:?}", expected, actual);
+    }
+
+    #[test]
+    fn basis_looking_along_z() {
+        let camera = Camera::new(Vector3::new(1.0, 2.0, 3.0), Vector3::new(1.0, 2.0, 10.0), Vector3::new(0.0, 1.0, 0.0), 60.0);
+        let (right, up, forward) = camera.basis();
+        assert_vec_eq(right, Vector3::new(1.0, 0.0, 0.0));
+        assert_vec_eq(up, Vector3::new(0.0, 1.0, 0.0));
+        assert_vec_eq(forward, Vector3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn basis_is_orthonormal_for_tilted_up() {
+        // `up` isn't perpendicular to the view direction, so gets corrected
+        let camera = Camera::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, -1.0, 0.0), Vector3::new(0.0, 1.0, 0.0), 60.0);
+        let (right, up, forward) = camera.basis();
+        assert_vec_eq(forward, Vector3::new(1.0, -1.0, 0.0).normalize());
+        assert_vec_eq(right, Vector3::new(0.0, 0.0, -1.0));
+        assert_vec_eq(up, Vector3::new(1.0, 1.0, 0.0).normalize());
+    }
+
+    #[test]
+    fn orientation_rotates_camera_axes_to_basis() {
+        let camera = Camera::new(Vector3::new(0.0, 1.0, 0.0), Vector3::new(3.0, 0.0, 4.0), Vector3::new(0.0, 1.0, 0.0), 45.0);
+        let (right, up, forward) = camera.basis();
+        let orientation = camera.orientation();
+        assert_vec_eq(orientation.rotate_vector(Vector3::new(1.0, 0.0, 0.0)), right);
+        assert_vec_eq(orientation.rotate_vector(Vector3::new(0.0, 1.0, 0.0)), up);
+        assert_vec_eq(orientation.rotate_vector(Vector3::new(0.0, 0.0, 1.0)), forward);
+    }
+
+    #[test]
+    fn new_focuses_at_look_at() {
+        let camera = Camera::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 3.0, 4.0), Vector3::new(0.0, 1.0, 0.0), 45.0);
+        assert_eq!(camera.focus_distance, 5.0);
+        assert_eq!(camera.aperture, 0.0);
+    }
+}
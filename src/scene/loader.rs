@@ -1,79 +1,158 @@
 use yaml_rust::{YamlLoader, Yaml};
+use yaml_rust::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust::scanner::Marker;
+use std::collections::HashMap;
 use std::fs;
-use crate::cgmath::{Quaternion, Vector3};
-use yaml_rust::yaml::Array;
-use crate::base::color::Color;
-use crate::scene::scene::{Light, Scene, Specs, Sphere};
-
-// Constructs a scene by loading its data from a yaml file.
-pub fn load(filepath: &str) -> Option<Scene> {
-
-    // Load file as string
-    let string = match fs::read_to_string(filepath) {
-        Err(_) => {
-            println!("could not make scene (file error)");
-            return None;
-        },
-        Ok(string) => string
-    };
+use std::path::Path;
+use crate::cgmath::{Euler, InnerSpace, Quaternion, Vector3};
+use crate::base::color::{Color, OutputTransfer};
+use crate::base::tone_mapping::ToneMapping;
+use crate::quaternion_from_euler;
+use crate::scene::camera::{Camera, Projection, Stereo, StereoLayout};
+use crate::scene::mesh::{Mesh, MeshSource};
+use crate::scene::moving::Moving;
+use crate::scene::obj_loader;
+use crate::scene::scene::{Attenuation, Light, Plane, SamplePattern, Scene, Specs, Sphere};
+use crate::scene::scene_error::{SceneError, SceneErrorKind};
+use crate::scene::shape::{Material, Shape};
+use crate::scene::texture::{Pattern, PatternKind, Texture, TextureSpace};
+
+/// Constructs a scene by loading its data from a yaml file.
+/// Lights and objects which are invalid get reported to stdout, and skipped.
+pub fn load(filepath: &str) -> Result<Scene, SceneError> {
+    load_file(filepath, false)
+}
+
+/// Like `load`, but fails on invalid lights and objects rather than skipping them.
+pub fn load_strict(filepath: &str) -> Result<Scene, SceneError> {
+    load_file(filepath, true)
+}
+
+/// Constructs a scene from a yaml string.
+/// Relative file paths in the scene (ie, meshes) are resolved against `base_dir`.
+pub fn load_from_str(string: &str, base_dir: &Path, strict: bool) -> Result<Scene, SceneError> {
 
     // Make yaml document
-    let docs = match YamlLoader::load_from_str(&string) {
-        Err(_) => {
-            println!("could not make scene (scan error)");
-            return None;
-        },
-        Ok(docs) => docs
+    let docs = YamlLoader::load_from_str(string).map_err(|e| {
+        let marker = e.marker();
+        // The message ends with the location, which gets reported separately
+        let mut message = e.to_string();
+        if let Some(i) = message.rfind(" at line ") {
+            message.truncate(i);
+        }
+        SceneError {
+            kind: SceneErrorKind::Syntax(message),
+            path: String::new(),
+            location: Some((marker.line(), marker.col() + 1)),
+        }
+    })?;
+    let doc = match docs.first() {
+        None => return Err(SceneError {
+            kind: SceneErrorKind::Syntax("empty document".to_string()),
+            path: String::new(),
+            location: None,
+        }),
+        Some(doc) => doc
     };
-    let doc = &docs[0];
 
-    // Make specs object
-    let specs = match make_specs(doc) {
-        None => {
-            println!("could not make scene (specs object)");
-            return None;
-        },
-        Some(specs) => specs
-    };
+    // Make lookup of each value's position in the file, for error reporting
+    let mut markers = Markers::default();
+    let _ = Parser::new(string.chars()).load(&mut markers, false);
+    let root = Node { yaml: doc, path: String::new(), markers: &markers };
+    root.as_hash()?;
 
-    // Make lights collection
-    let lights = match make_lights(doc) {
-        None => {
-            println!("could not make scene (lights array)");
-            return None;
-        },
-        Some(lights) => lights
+    let mut specs = make_specs(&root.get("specs"))?;
+    specs.camera = make_camera(&root.get("camera"))?;
+    let lights = match root.get("lights") {
+        node if node.is_missing() => Vec::new(),
+        node => make_lights(&node, strict)?
     };
-
-    // Make objects (spheres) collection
-    let spheres = match make_objects(doc) {
-        None => {
-            println!("could not make scene (objects array)");
-            return None;
-        },
-        Some(spheres) => spheres
+    // Objects' material properties fall back to the optional `defaults` block
+    let defaults = match root.get("defaults") {
+        node if node.is_missing() => Material::new_with_defaults(),
+        node => make_material(&node, &Material::new_with_defaults())?
     };
+    let objects = match root.get("objects") {
+        node if node.is_missing() => Vec::new(),
+        node => make_objects(&node, &defaults, base_dir, strict)?
+    };
+
+    let scene = Scene { specs, lights, objects };
+    Ok(scene)
+}
 
-    // Return scene
-    let scene = Scene { specs, lights, spheres };
-    Some(scene)
+fn load_file(filepath: &str, strict: bool) -> Result<Scene, SceneError> {
+    let string = fs::read_to_string(filepath).map_err(|e| SceneError {
+        kind: SceneErrorKind::File(format!("{}: {}", filepath, e)),
+        path: String::new(),
+        location: None,
+    })?;
+    // Mesh file paths are relative to the scene file's directory.
+    let base_dir = Path::new(filepath).parent().unwrap_or_else(|| Path::new(""));
+    load_from_str(&string, base_dir, strict)
 }
 
-fn make_specs(doc: &Yaml) -> Option<Specs> {
-    let specs = &doc["specs"];
+/// Any omitted values fall back to those of `Specs::new_with_defaults()`
+fn make_specs(specs: &Node) -> Result<Specs, SceneError> {
+    let defaults = Specs::new_with_defaults();
+    if specs.is_missing() {
+        return Ok(defaults);
+    }
+    specs.as_hash()?;
 
-    let viewport_width = specs["viewport_width"].as_f64()?;
-    let viewport_height = specs["viewport_height"].as_f64()?;
-    let viewport_distance = specs["viewport_distance"].as_f64()?;
-    let canvas_width = specs["canvas_width"].as_f64()?;
-    let canvas_height = specs["canvas_height"].as_f64()?;
-    let pixel_ar = specs["pixel_ar"].as_f64()?;
-    let camera_pos = specs["camera_pos"].as_vec()?;
-    let camera_pos = make_vec3(camera_pos)?;
-    let camera_orientation = specs["camera_orientation"].as_vec()?;
-    let camera_orientation: Quaternion<f64> = make_quat(&camera_orientation)?;
-    let background_color = specs["background_color"].as_vec()?;
-    let background_color = make_color(background_color)?;
+    let viewport_width = specs.get("viewport_width").as_f64_or(defaults.viewport_width)?;
+    let viewport_height = specs.get("viewport_height").as_f64_or(defaults.viewport_height)?;
+    let viewport_distance = specs.get("viewport_distance").as_f64_or(defaults.viewport_distance)?;
+    let canvas_width = specs.get("canvas_width").as_f64_or(defaults.canvas_width)?;
+    let canvas_height = specs.get("canvas_height").as_f64_or(defaults.canvas_height)?;
+    let pixel_ar = specs.get("pixel_ar").as_f64_or(defaults.pixel_ar)?;
+    let camera_pos = optional(&specs.get("camera_pos"), defaults.camera_pos, make_vec3)?;
+    let camera_orientation = optional(&specs.get("camera_orientation"), defaults.camera_orientation, make_quat)?;
+    let projection = optional(&specs.get("projection"), defaults.projection, make_projection)?;
+    let stereo = match specs.get("stereo") {
+        node if node.is_missing() => defaults.stereo,
+        node => Some(make_stereo(&node)?)
+    };
+    let shutter_open_node = specs.get("shutter_open");
+    let shutter_open = shutter_open_node.as_f64_or(defaults.shutter_open)?;
+    if !(0.0..=1.0).contains(&shutter_open) {
+        return Err(shutter_open_node.invalid("expected time between 0.0 and 1.0"));
+    }
+    let shutter_close_node = specs.get("shutter_close");
+    let shutter_close = shutter_close_node.as_f64_or(shutter_open.max(defaults.shutter_close))?;
+    if !(shutter_open..=1.0).contains(&shutter_close) {
+        return Err(shutter_close_node.invalid("expected time between shutter_open and 1.0"));
+    }
+    let camera_velocity = optional(&specs.get("camera_velocity"), defaults.camera_velocity, make_vec3)?;
+    let background_color = optional(&specs.get("background_color"), defaults.background_color, make_hdr_color)?;
+    let samples_per_pixel = optional(&specs.get("samples_per_pixel"), defaults.samples_per_pixel, make_count)?;
+    let sample_pattern = match specs.get("sample_pattern") {
+        node if node.is_missing() => defaults.sample_pattern,
+        node => match node.as_str()? {
+            "grid" => SamplePattern::Grid,
+            "jittered" => SamplePattern::Jittered,
+            _ => return Err(node.invalid("expected one of: grid, jittered"))
+        }
+    };
+    let light_samples = optional(&specs.get("light_samples"), defaults.light_samples, make_count)?;
+    let tone_mapping = match specs.get("tone_mapping") {
+        node if node.is_missing() => defaults.tone_mapping,
+        node => match node.as_str()? {
+            "clamp" => ToneMapping::Clamp,
+            "reinhard" => ToneMapping::Reinhard,
+            "aces" => ToneMapping::AcesFilmic,
+            _ => return Err(node.invalid("expected one of: clamp, reinhard, aces"))
+        }
+    };
+    let exposure = specs.get("exposure").as_f64_or(defaults.exposure)?;
+    let output_transfer = match specs.get("output_transfer") {
+        node if node.is_missing() => defaults.output_transfer,
+        node => match node.as_str()? {
+            "linear" => OutputTransfer::Linear,
+            "srgb" => OutputTransfer::Srgb,
+            _ => return Err(node.invalid("expected one of: linear, srgb"))
+        }
+    };
 
     let specs = Specs {
         viewport_width,
@@ -84,121 +163,693 @@ fn make_specs(doc: &Yaml) -> Option<Specs> {
         pixel_ar,
         camera_pos,
         camera_orientation,
+        camera: None, // From the top-level camera block
+        projection,
+        stereo,
+        shutter_open,
+        shutter_close,
+        camera_velocity,
         background_color,
+        samples_per_pixel,
+        sample_pattern,
+        light_samples,
+        tone_mapping,
+        exposure,
+        output_transfer,
     };
-    Some(specs)
+    Ok(specs)
 }
 
-fn make_lights(doc: &Yaml) -> Option<Vec<Light>> {
-    let lights = &doc["lights"];
-    if !lights.is_array() {
-        return None;
+/// The camera block is optional
+fn make_camera(camera: &Node) -> Result<Option<Camera>, SceneError> {
+    if camera.is_missing() {
+        return Ok(None);
     }
-    let lights = lights.as_vec().unwrap();
+    camera.as_hash()?;
 
+    let position = make_vec3(&camera.get("position"))?;
+    let look_at_node = camera.get("look_at");
+    let look_at = make_vec3(&look_at_node)?;
+    if look_at == position {
+        return Err(look_at_node.invalid("expected a point other than the camera's position"));
+    }
+    let up_node = camera.get("up");
+    let up = optional(&up_node, Vector3::<f64>::new(0.0, 1.0, 0.0), make_vec3)?;
+    if up.cross(look_at - position).magnitude2() == 0.0 {
+        return Err(up_node.invalid("expected a direction which isn't parallel to the view direction"));
+    }
+    let fov_node = camera.get("fov");
+    let fov = fov_node.as_f64_or(60.0)?;
+    if !(fov > 0.0 && fov < 180.0) {
+        return Err(fov_node.invalid("expected angle greater than 0 and less than 180 degrees"));
+    }
+    let mut result = Camera::new(position, look_at, up, fov);
+    // Depth of field is optional; by default, the focus is at the look-at point
+    let aperture_node = camera.get("aperture");
+    result.aperture = aperture_node.as_f64_or(0.0)?;
+    if result.aperture < 0.0 {
+        return Err(aperture_node.invalid("expected aperture of at least 0"));
+    }
+    let focus_node = camera.get("focus_distance");
+    result.focus_distance = focus_node.as_f64_or(result.focus_distance)?;
+    if result.focus_distance <= 0.0 {
+        return Err(focus_node.invalid("expected distance greater than 0"));
+    }
+    Ok(Some(result))
+}
+
+/// Projection is either `perspective` or `equirectangular`, or a hash with the projection type as its key, eg:
+/// `{ orthographic: { height: 4.0 } }`, `{ fisheye: { fov: 180.0 } }`
+fn make_projection(projection: &Node) -> Result<Projection, SceneError> {
+    if !matches!(projection.yaml, Yaml::Hash(_)) {
+        return match projection.as_str()? {
+            "perspective" => Ok(Projection::Perspective),
+            "equirectangular" => Ok(Projection::Equirectangular),
+            _ => Err(projection.invalid("expected perspective, equirectangular, or a hash with key: orthographic, fisheye"))
+        };
+    }
+    let orthographic = projection.get("orthographic");
+    if !orthographic.is_missing() {
+        orthographic.as_hash()?;
+        let height_node = orthographic.get("height");
+        let height = height_node.as_f64()?;
+        if height <= 0.0 {
+            return Err(height_node.invalid("expected height greater than 0"));
+        }
+        return Ok(Projection::Orthographic { height });
+    }
+    let fisheye = projection.get("fisheye");
+    if !fisheye.is_missing() {
+        fisheye.as_hash()?;
+        let fov_node = fisheye.get("fov");
+        let fov = fov_node.as_f64()?;
+        if !(fov > 0.0 && fov <= 360.0) {
+            return Err(fov_node.invalid("expected angle greater than 0 and at most 360 degrees"));
+        }
+        return Ok(Projection::Fisheye { fov });
+    }
+    Err(projection.invalid("unknown projection type, expected one of: orthographic, fisheye"))
+}
+
+fn make_stereo(stereo: &Node) -> Result<Stereo, SceneError> {
+    stereo.as_hash()?;
+    let layout_node = stereo.get("layout");
+    let layout = match layout_node.as_str()? {
+        "side_by_side" => StereoLayout::SideBySide,
+        "top_bottom" => StereoLayout::TopBottom,
+        "anaglyph" => StereoLayout::Anaglyph,
+        _ => return Err(layout_node.invalid("expected one of: side_by_side, top_bottom, anaglyph"))
+    };
+    let separation_node = stereo.get("eye_separation");
+    let eye_separation = separation_node.as_f64()?;
+    if eye_separation < 0.0 {
+        return Err(separation_node.invalid("expected distance of at least 0"));
+    }
+    let convergence_node = stereo.get("convergence");
+    let convergence = convergence_node.as_f64()?;
+    if convergence <= 0.0 {
+        return Err(convergence_node.invalid("expected distance greater than 0"));
+    }
+    Ok(Stereo { layout, eye_separation, convergence })
+}
+
+fn make_lights(lights: &Node, strict: bool) -> Result<Vec<Light>, SceneError> {
     let mut result = Vec::<Light>::new();
-    for light in  lights {
-        let light = make_light(light);
-        if let None = light {
-            println!("bad light object, skipping");
-            continue;
+    for light in lights.as_array()? {
+        match make_light(&light) {
+            Ok(light) => result.push(light),
+            Err(e) if strict => return Err(e),
+            Err(e) => println!("bad light object, skipping: {}", e)
         }
-        result.push(light.unwrap());
     }
-    Some(result)
+    Ok(result)
 }
 
-fn make_light(light: &Yaml) -> Option<Light> {
+fn make_light(light: &Node) -> Result<Light, SceneError> {
+    light.as_hash()?;
 
-    let ambient_light = &light["ambient"];
-    if let Some(_) = ambient_light.as_hash() {
-        let intensity = ambient_light["intensity"].as_f64()?;
-        let result = Some(Light::Ambient { intensity });
-        return result;
+    let ambient_light = light.get("ambient");
+    if !ambient_light.is_missing() {
+        ambient_light.as_hash()?;
+        let intensity = ambient_light.get("intensity").as_f64()?;
+        let color = make_light_color(&ambient_light)?;
+        let result = Light::Ambient { intensity, color };
+        return Ok(result);
     }
-    let point_light = &light["point"];
-    if let Some(_) = point_light.as_hash() {
-        let intensity = point_light["intensity"].as_f64()?;
-        let position = point_light["position"].as_vec()?;
-        let position = make_vec3(position)?;
-        let result = Some(Light::Point { intensity, position });
-        return result;
+    let point_light = light.get("point");
+    if !point_light.is_missing() {
+        point_light.as_hash()?;
+        let intensity = point_light.get("intensity").as_f64()?;
+        let color = make_light_color(&point_light)?;
+        let position = make_vec3(&point_light.get("position"))?;
+        let (attenuation, range) = make_attenuation(&point_light)?;
+        let velocity = make_velocity(&point_light)?;
+        let result = Light::Point { intensity, color, position, attenuation, range, velocity };
+        return Ok(result);
     }
-    let directional_light = &light["directional"];
-    if let Some(_) = directional_light.as_hash() {
-        let intensity = directional_light["intensity"].as_f64()?;
-        let direction = directional_light["direction"].as_vec()?;
-        let direction = make_vec3(direction)?;
-        let result = Some(Light::Directional { intensity, direction });
-        return result;
+    let directional_light = light.get("directional");
+    if !directional_light.is_missing() {
+        directional_light.as_hash()?;
+        let intensity = directional_light.get("intensity").as_f64()?;
+        let color = make_light_color(&directional_light)?;
+        let direction = make_vec3(&directional_light.get("direction"))?;
+        let result = Light::Directional { intensity, color, direction };
+        return Ok(result);
     }
-    None
+    let spot_light = light.get("spot");
+    if !spot_light.is_missing() {
+        spot_light.as_hash()?;
+        let intensity = spot_light.get("intensity").as_f64()?;
+        let color = make_light_color(&spot_light)?;
+        let position = make_vec3(&spot_light.get("position"))?;
+        let direction = make_direction(&spot_light.get("direction"))?;
+        let outer_node = spot_light.get("outer_angle");
+        let outer_angle = outer_node.as_f64()?;
+        if !(outer_angle > 0.0 && outer_angle <= 180.0) {
+            return Err(outer_node.invalid("expected angle greater than 0 and at most 180 degrees"));
+        }
+        // Inner angle is optional; by default, the cone has a hard edge
+        let inner_node = spot_light.get("inner_angle");
+        let inner_angle = inner_node.as_f64_or(outer_angle)?;
+        if !(0.0..=outer_angle).contains(&inner_angle) {
+            return Err(inner_node.invalid("expected angle between 0 degrees and outer_angle"));
+        }
+        let (attenuation, range) = make_attenuation(&spot_light)?;
+        let velocity = make_velocity(&spot_light)?;
+        let result = Light::Spot {
+            intensity, color, position, direction, inner_angle, outer_angle, attenuation, range, velocity
+        };
+        return Ok(result);
+    }
+    let rect_light = light.get("rect");
+    if !rect_light.is_missing() {
+        rect_light.as_hash()?;
+        let intensity = rect_light.get("intensity").as_f64()?;
+        let color = make_light_color(&rect_light)?;
+        let position = make_vec3(&rect_light.get("position"))?;
+        let edge_u = make_vec3(&rect_light.get("edge_u"))?;
+        let edge_v = make_vec3(&rect_light.get("edge_v"))?;
+        let velocity = make_velocity(&rect_light)?;
+        let result = Light::Rect { intensity, color, position, edge_u, edge_v, velocity };
+        return Ok(result);
+    }
+    let sphere_light = light.get("sphere");
+    if !sphere_light.is_missing() {
+        sphere_light.as_hash()?;
+        let intensity = sphere_light.get("intensity").as_f64()?;
+        let color = make_light_color(&sphere_light)?;
+        let position = make_vec3(&sphere_light.get("position"))?;
+        let radius_node = sphere_light.get("radius");
+        let radius = radius_node.as_f64()?;
+        if radius < 0.0 {
+            return Err(radius_node.invalid("expected radius of at least 0"));
+        }
+        let velocity = make_velocity(&sphere_light)?;
+        let result = Light::Sphere { intensity, color, position, radius, velocity };
+        return Ok(result);
+    }
+    Err(light.invalid("unknown light type, expected one of: ambient, point, directional, spot, rect, sphere"))
 }
 
-fn make_objects(doc: &Yaml) -> Option<Vec<Sphere>> {
-    let objects = &doc["objects"];
-    if !objects.is_array() {
-        return None;
+/// Attenuation is optional, and is either `none`, `inverse_square`,
+/// or a hash of coefficients (`constant`, `linear`, `quadratic`). Range is also optional.
+fn make_attenuation(light: &Node) -> Result<(Attenuation, Option<f64>), SceneError> {
+    let attenuation = match light.get("attenuation") {
+        node if node.is_missing() => Attenuation::None,
+        node if matches!(node.yaml, Yaml::Hash(_)) => {
+            let constant = node.get("constant").as_f64_or(1.0)?;
+            let linear = node.get("linear").as_f64_or(0.0)?;
+            let quadratic = node.get("quadratic").as_f64_or(0.0)?;
+            if constant < 0.0 || linear < 0.0 || quadratic < 0.0 {
+                return Err(node.invalid("expected coefficients of at least 0"));
+            }
+            if constant == 0.0 && linear == 0.0 && quadratic == 0.0 {
+                return Err(node.invalid("expected at least one coefficient greater than 0"));
+            }
+            Attenuation::Coefficients { constant, linear, quadratic }
+        },
+        node => match node.as_str()? {
+            "none" => Attenuation::None,
+            "inverse_square" => Attenuation::InverseSquare,
+            _ => return Err(node.invalid("expected one of: none, inverse_square, or a hash of coefficients"))
+        }
+    };
+    let range = match light.get("range") {
+        node if node.is_missing() => None,
+        node => {
+            let range = node.as_f64()?;
+            if range <= 0.0 {
+                return Err(node.invalid("expected range greater than 0"));
+            }
+            Some(range)
+        }
+    };
+    Ok((attenuation, range))
+}
+
+/// Velocity is optional (for lights, and objects), and defaults to none
+fn make_velocity(node: &Node) -> Result<Vector3<f64>, SceneError> {
+    optional(&node.get("velocity"), Vector3::<f64>::new(0.0, 0.0, 0.0), make_vec3)
+}
+
+/// Wraps the shape in a `Moving` if it has a velocity
+fn make_moving(node: &Node, shape: Box<dyn Shape>) -> Result<Box<dyn Shape>, SceneError> {
+    if node.get("velocity").is_missing() {
+        return Ok(shape);
     }
-    let objects = objects.as_vec().unwrap();
+    Ok(Box::new(Moving::new(shape, make_velocity(node)?)))
+}
 
-    let mut result = Vec::<Sphere>::new();
-    for object in objects {
-        let sphere = make_object(object); // only spheres, for now
-        if let None = sphere {
-            println!("bad object, skipping");
-            continue;
+/// Light color is optional, and defaults to white
+fn make_light_color(light: &Node) -> Result<Color, SceneError> {
+    optional(&light.get("color"), Color::new(1.0, 1.0, 1.0), make_hdr_color)
+}
+
+fn make_objects(
+        objects: &Node,
+        defaults: &Material,
+        base_dir: &Path,
+        strict: bool) -> Result<Vec<Box<dyn Shape>>, SceneError> {
+
+    let mut result = Vec::<Box<dyn Shape>>::new();
+    for object in objects.as_array()? {
+        match make_object(&object, defaults, base_dir) {
+            Ok(object) => result.push(object),
+            Err(e) if strict => return Err(e),
+            Err(e) => println!("bad object, skipping: {}", e)
         }
-        result.push(sphere.unwrap());
     }
-    Some(result)
+    Ok(result)
 }
 
-fn make_object(object: &Yaml) -> Option<Sphere> {
-    // For now, sphere is the only type of object supported
-    let sphere = &object["sphere"];
-    if let Some(_) = sphere.as_hash() {
-        let center = sphere["center"].as_vec()?;
-        let center = make_vec3(center)?;
-        let radius = sphere["radius"].as_f64()?;
-        let color = sphere["color"].as_vec()?;
-        let color = make_color(color)?;
-        let specular = sphere["specular"].as_f64()?;
-        let reflective = sphere["reflective"].as_f64()?;
-        let transparency = sphere["transparency"].as_f64()?;
-        let result = Some(Sphere { center, radius, color, specular, reflective, transparency });
-        return result;
+fn make_object(object: &Node, defaults: &Material, base_dir: &Path) -> Result<Box<dyn Shape>, SceneError> {
+    object.as_hash()?;
+
+    let sphere = object.get("sphere");
+    if !sphere.is_missing() {
+        sphere.as_hash()?;
+        let center = make_vec3(&sphere.get("center"))?;
+        let radius_node = sphere.get("radius");
+        let radius = radius_node.as_f64()?;
+        if radius.is_nan() || radius <= 0.0 {
+            return Err(radius_node.invalid("expected radius greater than 0"));
+        }
+        let material = make_material(&sphere, defaults)?;
+        let result = Box::new(Sphere { center, radius, material });
+        return make_moving(&sphere, result);
+    }
+    let plane = object.get("plane");
+    if !plane.is_missing() {
+        plane.as_hash()?;
+        let point = make_vec3(&plane.get("point"))?;
+        let normal = make_direction(&plane.get("normal"))?;
+        let material = make_material(&plane, defaults)?;
+        let result = Box::new(Plane { point, normal, material });
+        return make_moving(&plane, result);
     }
-    None
+    let mesh = object.get("mesh");
+    if !mesh.is_missing() {
+        mesh.as_hash()?;
+        let file_node = mesh.get("file");
+        let file = file_node.as_str()?;
+        let triangles = obj_loader::load(&base_dir.join(file).to_string_lossy())
+            .map_err(|message| file_node.invalid(&message))?;
+        let position = make_vec3(&mesh.get("position"))?;
+        // Scale is optional, and can be either uniform or per-axis
+        let scale_node = mesh.get("scale");
+        let scale = match &scale_node {
+            node if node.is_missing() => Vector3::<f64>::new(1.0, 1.0, 1.0),
+            node if node.yaml.is_array() => make_vec3(node)?,
+            node => {
+                let scale = node.as_f64()?;
+                Vector3::<f64>::new(scale, scale, scale)
+            }
+        };
+        // Normals get divided by the scale
+        if [scale.x, scale.y, scale.z].iter().any(|value| *value == 0.0 || value.is_nan()) {
+            return Err(scale_node.invalid("expected scale with no components of 0"));
+        }
+        // Rotation is optional, and is expressed as euler angles in degrees
+        let degrees = optional(&mesh.get("rotation"), Vector3::<f64>::new(0.0, 0.0, 0.0), make_vec3)?;
+        let euler = Euler::<f64>::new(degrees.x.to_radians(), degrees.y.to_radians(), degrees.z.to_radians());
+        let rotation = quaternion_from_euler(euler).normalize();
+        let material = make_material(&mesh, defaults)?;
+        let mut result = Mesh::new(triangles, material);
+        result.transform(position, scale, rotation);
+        result.source = Some(MeshSource { file: file.to_string(), position, scale, rotation: degrees });
+        return make_moving(&mesh, Box::new(result));
+    }
+    Err(object.invalid("unknown object type, expected one of: sphere, plane, mesh"))
 }
 
-fn make_vec3(array: &Array) -> Option<Vector3<f64>> {
-    if array.len() != 3 {
-        return None;
+/// Makes the material from the surface properties common to all object types.
+/// Any omitted values fall back to those of `defaults`.
+fn make_material(object: &Node, defaults: &Material) -> Result<Material, SceneError> {
+    let color = optional(&object.get("color"), defaults.color, make_texture)?;
+    let specular = object.get("specular").as_f64_or(defaults.specular)?;
+    let reflective = object.get("reflective").as_f64_or(defaults.reflective)?;
+    let transparency = object.get("transparency").as_f64_or(defaults.transparency)?;
+    let ior_node = object.get("ior");
+    let ior = ior_node.as_f64_or(defaults.ior)?;
+    if ior.is_nan() || ior <= 0.0 {
+        return Err(ior_node.invalid("expected ior greater than 0"));
     }
+    Ok(Material { color, specular, reflective, transparency, ior })
+}
+
+/// Makes the value using `make`, or returns `default` if the value is omitted
+fn optional<T>(node: &Node, default: T, make: fn(&Node) -> Result<T, SceneError>) -> Result<T, SceneError> {
+    if node.is_missing() {
+        Ok(default)
+    } else {
+        make(node)
+    }
+}
+
+fn make_vec3(node: &Node) -> Result<Vector3<f64>, SceneError> {
+    let array = node.as_fixed_array(3, "array of 3 numbers")?;
     let x = array[0].as_f64()?;
     let y = array[1].as_f64()?;
     let z = array[2].as_f64()?;
-    Some(Vector3::<f64>::new(x, y, z))
+    Ok(Vector3::<f64>::new(x, y, z))
+}
+
+/// Integer of at least 1
+fn make_count(node: &Node) -> Result<usize, SceneError> {
+    let value = node.as_i64()?;
+    if value < 1 {
+        return Err(node.invalid("expected integer of at least 1"));
+    }
+    Ok(value as usize)
 }
 
-fn make_quat(array: &Array) -> Option<Quaternion<f64>> {
-    if array.len() != 4 {
-        return None;
+/// Like `make_vec3`, but rejects a zero-length vector, since it gets normalized
+fn make_direction(node: &Node) -> Result<Vector3<f64>, SceneError> {
+    let vector = make_vec3(node)?;
+    if vector.magnitude2().is_nan() || vector.magnitude2() == 0.0 {
+        return Err(node.invalid("expected non-zero vector"));
     }
+    Ok(vector)
+}
+
+fn make_quat(node: &Node) -> Result<Quaternion<f64>, SceneError> {
+    let array = node.as_fixed_array(4, "array of 4 numbers")?;
     let w = array[0].as_f64()?;
     let x = array[1].as_f64()?;
     let y = array[2].as_f64()?;
     let z = array[3].as_f64()?;
-    Some(Quaternion::<f64>::new(w, x, y, z))
+    Ok(Quaternion::<f64>::new(w, x, y, z))
+}
+
+/// Texture is either a color, or a hash with the pattern type as its key, eg:
+/// `{ checker: { colors: [[255, 255, 255], [0, 0, 0]], scale: 0.5 } }`
+fn make_texture(node: &Node) -> Result<Texture, SceneError> {
+    if !matches!(node.yaml, Yaml::Hash(_)) {
+        return Ok(Texture::Solid(make_color(node)?));
+    }
+    for name in ["checker", "stripes", "gradient", "noise", "marble", "wood"] {
+        let pattern = node.get(name);
+        if pattern.is_missing() {
+            continue;
+        }
+        pattern.as_hash()?;
+        let kind = match name {
+            "checker" => PatternKind::Checker,
+            "stripes" => PatternKind::Stripes,
+            "gradient" => PatternKind::Gradient,
+            "noise" => PatternKind::Noise,
+            "marble" => PatternKind::Marble { turbulence: pattern.get("turbulence").as_f64_or(1.0)? },
+            _ => PatternKind::Wood { turbulence: pattern.get("turbulence").as_f64_or(1.0)? },
+        };
+        let colors = pattern.get("colors").as_fixed_array(2, "array of 2 colors")?;
+        let colors = [make_color(&colors[0])?, make_color(&colors[1])?];
+        let scale_node = pattern.get("scale");
+        let scale = scale_node.as_f64_or(1.0)?;
+        if scale <= 0.0 {
+            return Err(scale_node.invalid("expected scale greater than 0"));
+        }
+        let space = match pattern.get("space") {
+            space if space.is_missing() => TextureSpace::Object,
+            space => match space.as_str()? {
+                "object" => TextureSpace::Object,
+                "uv" => TextureSpace::Uv,
+                _ => return Err(space.invalid("expected one of: object, uv"))
+            }
+        };
+        return Ok(Texture::Pattern(Pattern { kind, colors, scale, space }));
+    }
+    Err(node.invalid("unknown pattern type, expected one of: checker, stripes, gradient, noise, marble, wood"))
+}
+
+/// Colors are either 3 integers in the range 0-255, which are taken to be sRGB-encoded
+/// (as from a color picker), or 3 numbers in the range 0.0-1.0, which are taken to be linear
+fn make_color(node: &Node) -> Result<Color, SceneError> {
+    make_color_with_limit(node, false)
+}
+
+/// Like `make_color`, but the numbers may be above 1.0 (ie, HDR), as for the color of a light
+/// or the background, which is radiance rather than a surface's reflectance
+fn make_hdr_color(node: &Node) -> Result<Color, SceneError> {
+    make_color_with_limit(node, true)
+}
+
+fn make_color_with_limit(node: &Node, hdr: bool) -> Result<Color, SceneError> {
+    let array = node.as_fixed_array(3, "array of 3 integers (0-255) or 3 numbers (0.0-1.0)")?;
+    let num_reals = array.iter().filter(|item| matches!(item.yaml, Yaml::Real(_))).count();
+    if num_reals == 3 {
+        let (max, message) = if hdr {
+            (f64::INFINITY, "expected number of at least 0.0")
+        } else {
+            (1.0, "expected number in the range 0.0-1.0")
+        };
+        let mut values = [0.0; 3];
+        for (i, item) in array.iter().enumerate() {
+            let value = item.as_f64()?;
+            if !value.is_finite() || !(0.0..=max).contains(&value) {
+                return Err(item.invalid(message));
+            }
+            values[i] = value;
+        }
+        return Ok(Color::new(values[0], values[1], values[2]));
+    }
+    if num_reals > 0 {
+        return Err(node.invalid("expected either 3 integers (0-255) or 3 numbers (0.0-1.0), not a mix"));
+    }
+    let mut values = [0_u8; 3];
+    for (i, item) in array.iter().enumerate() {
+        let value = item.as_i64()?;
+        if !(0..=255).contains(&value) {
+            return Err(item.invalid("expected integer in the range 0-255"));
+        }
+        values[i] = value as u8;
+    }
+    Ok(Color::from_srgb_u8(values[0], values[1], values[2]))
+}
+
+// ---
+
+/// A value within the yaml document, plus its path, so that errors can say where they occurred
+struct Node<'a> {
+    yaml: &'a Yaml,
+    path: String,
+    markers: &'a Markers,
+}
+
+impl<'a> Node<'a> {
+
+    fn get(&self, key: &str) -> Node<'a> {
+        let path = if self.path.is_empty() { key.to_string() } else { format!("{}.{}", self.path, key) };
+        Node { yaml: &self.yaml[key], path, markers: self.markers }
+    }
+
+    fn is_missing(&self) -> bool {
+        self.yaml.is_badvalue()
+    }
+
+    fn as_f64(&self) -> Result<f64, SceneError> {
+        match self.yaml {
+            Yaml::Real(_) => self.yaml.as_f64().ok_or_else(|| self.invalid("invalid number")),
+            Yaml::Integer(value) => Ok(*value as f64),
+            _ => Err(self.wrong_type("number"))
+        }
+    }
+
+    /// Returns `default` if the value is omitted
+    fn as_f64_or(&self, default: f64) -> Result<f64, SceneError> {
+        if self.is_missing() {
+            Ok(default)
+        } else {
+            self.as_f64()
+        }
+    }
+
+    fn as_i64(&self) -> Result<i64, SceneError> {
+        self.yaml.as_i64().ok_or_else(|| self.wrong_type("integer"))
+    }
+
+    fn as_str(&self) -> Result<&'a str, SceneError> {
+        self.yaml.as_str().ok_or_else(|| self.wrong_type("string"))
+    }
+
+    fn as_hash(&self) -> Result<(), SceneError> {
+        self.yaml.as_hash().map(|_| ()).ok_or_else(|| self.wrong_type("mapping"))
+    }
+
+    fn as_array(&self) -> Result<Vec<Node<'a>>, SceneError> {
+        let array = self.yaml.as_vec().ok_or_else(|| self.wrong_type("array"))?;
+        let result = array.iter()
+            .enumerate()
+            .map(|(i, yaml)| Node { yaml, path: format!("{}[{}]", self.path, i), markers: self.markers })
+            .collect();
+        Ok(result)
+    }
+
+    /// Array which must have exactly `length` items. `expected` describes it, for error reporting.
+    fn as_fixed_array(&self, length: usize, expected: &str) -> Result<Vec<Node<'a>>, SceneError> {
+        match self.yaml.as_vec() {
+            Some(array) if array.len() == length => self.as_array(),
+            _ => Err(self.wrong_type(expected))
+        }
+    }
+
+    fn wrong_type(&self, expected: &str) -> SceneError {
+        let kind = if self.is_missing() {
+            SceneErrorKind::Missing { expected: expected.to_string() }
+        } else {
+            SceneErrorKind::WrongType { expected: expected.to_string(), found: describe(self.yaml) }
+        };
+        self.error(kind)
+    }
+
+    fn invalid(&self, message: &str) -> SceneError {
+        self.error(SceneErrorKind::InvalidValue(message.to_string()))
+    }
+
+    fn error(&self, kind: SceneErrorKind) -> SceneError {
+        let location = self.markers.find(&self.path);
+        SceneError { kind, path: self.path.clone(), location }
+    }
+}
+
+/// Describes the type of a yaml value, for error reporting
+fn describe(yaml: &Yaml) -> String {
+    let description = match yaml {
+        Yaml::Real(_) | Yaml::Integer(_) => "number",
+        Yaml::String(_) => "string",
+        Yaml::Boolean(_) => "boolean",
+        Yaml::Array(_) => "array",
+        Yaml::Hash(_) => "mapping",
+        Yaml::Alias(_) => "alias",
+        Yaml::Null => "null",
+        Yaml::BadValue => "nothing",
+    };
+    match yaml {
+        Yaml::Array(array) if array.len() == 1 => "array with 1 item".to_string(),
+        Yaml::Array(array) => format!("array with {} items", array.len()),
+        _ => description.to_string()
+    }
+}
+
+/// Records the file position of every value in a yaml document, keyed by path
+/// (using the same path format as `Node`). Built by listening to the parser's events.
+///
+/// Mappings and sequences are positioned at their key (eg, `sphere:`) when they have one,
+/// and mappings within sequences at their first key, since that is where a reader would look.
+#[derive(Default)]
+struct Markers {
+    positions: HashMap<String, Marker>,
+    stack: Vec<Container>,
+    /// Position of the most recent mapping key
+    key_marker: Option<Marker>,
+}
+
+enum Container {
+    Mapping { path: String, key: Option<String>, is_positioned: bool },
+    Sequence { path: String, index: usize },
 }
 
-fn make_color(array: &Array) -> Option<Color> {
-    if array.len() != 3 {
-        return None;
+impl Markers {
+    /// Returns the 1-based line and column of the value at the given path. If there is no such value,
+    /// uses that of its closest ancestor.
+    fn find(&self, path: &str) -> Option<(usize, usize)> {
+        let mut path = path;
+        loop {
+            if let Some(marker) = self.positions.get(path) {
+                return Some((marker.line(), marker.col() + 1));
+            }
+            if path.is_empty() {
+                return None;
+            }
+            path = match path.rfind(['.', '[']) {
+                Some(i) => &path[..i],
+                None => ""
+            };
+        }
+    }
+
+    /// Returns the path for a value which is starting, or `None` if the value is a mapping key
+    fn next_value_path(&mut self, event: &Event) -> Option<String> {
+        match self.stack.last_mut() {
+            None => Some(String::new()),
+            Some(Container::Sequence { path, index }) => {
+                let result = format!("{}[{}]", path, index);
+                *index += 1;
+                Some(result)
+            },
+            Some(Container::Mapping { path, key, .. }) => match key.take() {
+                Some(key) => Some(if path.is_empty() { key } else { format!("{}.{}", path, key) }),
+                None => {
+                    *key = Some(match event {
+                        Event::Scalar(value, ..) => value.clone(),
+                        _ => "?".to_string() // complex key; not supported
+                    });
+                    None
+                }
+            }
+        }
+    }
+}
+
+impl MarkedEventReceiver for Markers {
+    fn on_event(&mut self, event: Event, marker: Marker) {
+        let key_marker = self.key_marker.take();
+        match event {
+            Event::Scalar(..) | Event::Alias(_) => {
+                match self.next_value_path(&event) {
+                    Some(path) => {
+                        self.positions.insert(path, marker);
+                    },
+                    None => {
+                        // Is a key. Also positions its mapping, if this is the mapping's first key.
+                        if let Some(Container::Mapping { path, is_positioned, .. }) = self.stack.last_mut() {
+                            if !*is_positioned {
+                                self.positions.insert(path.clone(), marker);
+                                *is_positioned = true;
+                            }
+                        }
+                        self.key_marker = Some(marker);
+                    }
+                }
+            },
+            Event::MappingStart(_) | Event::SequenceStart(_) => {
+                // Values inside a mapping key are not tracked, but still need a container on the stack
+                let path = self.next_value_path(&event).unwrap_or_else(|| "?".to_string());
+                if let Some(key_marker) = key_marker {
+                    self.positions.insert(path.clone(), key_marker);
+                }
+                let container = match event {
+                    Event::MappingStart(_) => Container::Mapping { path, key: None, is_positioned: key_marker.is_some() },
+                    _ => {
+                        self.positions.entry(path.clone()).or_insert(marker);
+                        Container::Sequence { path, index: 0 }
+                    }
+                };
+                self.stack.push(container);
+            },
+            Event::MappingEnd | Event::SequenceEnd => {
+                self.stack.pop();
+            },
+            _ => {}
+        }
     }
-    let r = array[0].as_i64()? as u8;
-    let g = array[1].as_i64()? as u8;
-    let b = array[2].as_i64()? as u8;
-    Some(Color::from_u8(r, g, b))
 }
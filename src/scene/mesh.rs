@@ -0,0 +1,148 @@
+use crate::cgmath::{InnerSpace, Quaternion, Rotation, Vector2, Vector3};
+use crate::scene::bvh::Bvh;
+use crate::scene::shape::{Aabb, Hit, Material, Ray, Shape};
+use crate::util::maths;
+
+/// Triangle with per-vertex normals, allowing for smooth shading.
+/// Vertices are expected to be in counter-clockwise winding order.
+#[derive(Clone, Copy, Debug)]
+pub struct Triangle {
+    pub vertices: [Vector3<f64>; 3],
+    pub normals: [Vector3<f64>; 3],
+}
+
+impl Triangle {
+    /// Makes a triangle whose vertex normals are all its face normal (ie, flat shading).
+    pub fn new_flat(a: Vector3<f64>, b: Vector3<f64>, c: Vector3<f64>) -> Triangle {
+        let n = face_normal(a, b, c);
+        Triangle { vertices: [a, b, c], normals: [n, n, n] }
+    }
+
+    /// The triangle's geometric normal (normalized), derived from its winding order.
+    pub fn face_normal(&self) -> Vector3<f64> {
+        face_normal(self.vertices[0], self.vertices[1], self.vertices[2])
+    }
+
+    /// Returns the 'distance' on a ray where it intersects the triangle (Möller–Trumbore),
+    /// along with the smooth-shaded normal there. Triangles are two-sided.
+    pub fn intersect(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit> {
+        let [v0, v1, v2] = self.vertices;
+        let edge1 = v1 - v0;
+        let edge2 = v2 - v0;
+        let h = ray.direction.cross(edge2);
+        let a = edge1.dot(h);
+        if a.abs() < f64::EPSILON {
+            return None; // parallel to the triangle
+        }
+        let f = 1.0 / a;
+        let s = ray.origin - v0;
+        let u = f * s.dot(h);
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = s.cross(edge1);
+        let v = f * ray.direction.dot(q);
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = f * edge2.dot(q);
+        if !maths::contains(t, t_min, t_max) {
+            return None;
+        }
+
+        // Interpolate the vertex normals using the barycentric coordinates
+        let [n0, n1, n2] = self.normals;
+        let mut normal = (n0 * (1.0 - u - v) + n1 * u + n2 * v).normalize();
+        // Near silhouettes, the interpolated normal can end up on the other side of the surface
+        // from the face normal, as seen by the ray. Fall back to the face normal in that case.
+        let face_normal = self.face_normal();
+        if (normal.dot(ray.direction) > 0.0) != (face_normal.dot(ray.direction) > 0.0) {
+            normal = face_normal;
+        }
+        // Meshes are already transformed into world space, which serves as their own space.
+        // Surface coordinates are the barycentric coordinates within the triangle.
+        Some(Hit { t, normal, local: ray.at(t), uv: Vector2::new(u, v) })
+    }
+}
+
+/// Collection of triangles sharing the same surface properties.
+///
+/// Holds its own `Bvh` over its triangles. If `triangles` gets modified directly,
+/// call `update_bvh()` afterwards.
+#[derive(Clone, Debug)]
+pub struct Mesh {
+    pub triangles: Vec<Triangle>,
+    pub material: Material,
+    /// Set when the mesh comes from a file, so it can be saved back to a scene file
+    pub source: Option<MeshSource>,
+    bvh: Bvh,
+}
+
+/// The OBJ file a mesh was loaded from, and the transform which was applied to it
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeshSource {
+    /// As written in the scene file (ie, relative to the scene file's directory)
+    pub file: String,
+    pub position: Vector3<f64>,
+    pub scale: Vector3<f64>,
+    /// Euler angles, in degrees
+    pub rotation: Vector3<f64>,
+}
+
+impl Mesh {
+    pub fn new(triangles: Vec<Triangle>, material: Material) -> Mesh {
+        let bvh = make_bvh(&triangles);
+        Mesh { triangles, material, source: None, bvh }
+    }
+
+    pub fn update_bvh(&mut self) {
+        self.bvh = make_bvh(&self.triangles);
+    }
+
+    /// Scales, then rotates, then translates all of the mesh's triangles, in place.
+    /// Scale components must not be 0.
+    pub fn transform(&mut self, position: Vector3<f64>, scale: Vector3<f64>, rotation: Quaternion<f64>) {
+        for triangle in &mut self.triangles {
+            for v in &mut triangle.vertices {
+                let scaled = Vector3::new(v.x * scale.x, v.y * scale.y, v.z * scale.z);
+                *v = rotation.rotate_vector(scaled) + position;
+            }
+            // Normals use the inverse scale, so they stay perpendicular to non-uniformly scaled surfaces
+            for n in &mut triangle.normals {
+                let scaled = Vector3::new(n.x / scale.x, n.y / scale.y, n.z / scale.z);
+                *n = rotation.rotate_vector(scaled).normalize();
+            }
+        }
+        self.update_bvh();
+    }
+}
+
+impl Shape for Mesh {
+    fn intersect(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit> {
+        self.bvh
+            .closest_hit(ray, t_min, t_max, |i, t_max| self.triangles[i].intersect(ray, t_min, t_max))
+            .map(|(hit, _)| hit)
+    }
+
+    fn bounds(&self) -> Aabb {
+        let points: Vec<Vector3<f64>> = self.triangles.iter()
+            .flat_map(|triangle| triangle.vertices)
+            .collect();
+        Aabb::from_points(&points)
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+}
+
+fn make_bvh(triangles: &[Triangle]) -> Bvh {
+    let bounds: Vec<Aabb> = triangles.iter()
+        .map(|triangle| Aabb::from_points(&triangle.vertices))
+        .collect();
+    Bvh::new(&bounds)
+}
+
+fn face_normal(a: Vector3<f64>, b: Vector3<f64>, c: Vector3<f64>) -> Vector3<f64> {
+    (b - a).cross(c - a).normalize()
+}
@@ -1,3 +1,13 @@
+#[allow(clippy::module_inception)]
 pub mod scene;
+pub mod scene_error;
+pub mod shape;
+pub mod texture;
+pub mod camera;
+pub mod bvh;
 pub mod loader;
+pub mod saver;
+pub mod mesh;
+pub mod moving;
+pub mod obj_loader;
 pub mod renderer;
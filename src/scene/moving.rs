@@ -0,0 +1,49 @@
+use crate::cgmath::Vector3;
+use crate::scene::shape::{Aabb, Hit, Material, Ray, Shape};
+
+/// Wraps a shape so that it moves in a straight line during the frame, for motion blur.
+///
+/// At time `t` (in frames), the shape is offset from where it is by `velocity * t`.
+/// Its bounds cover its motion between times 0.0 and 1.0 (ie, any shutter interval).
+#[derive(Debug)]
+pub struct Moving {
+    pub shape: Box<dyn Shape>,
+    /// In world units per frame
+    pub velocity: Vector3<f64>,
+}
+
+impl Moving {
+    pub fn new(shape: Box<dyn Shape>, velocity: Vector3<f64>) -> Moving {
+        Moving { shape, velocity }
+    }
+
+    /// Returns the wrapped shape as its concrete type,
+    /// or `None` if it is of some other type.
+    pub fn get_shape<T: Shape>(&self) -> Option<&T> {
+        self.shape.as_ref().as_any().downcast_ref::<T>()
+    }
+
+    /// Mutable version of `get_shape`, useful for animating objects.
+    pub fn get_shape_mut<T: Shape>(&mut self) -> Option<&mut T> {
+        self.shape.as_mut().as_any_mut().downcast_mut::<T>()
+    }
+}
+
+impl Shape for Moving {
+    fn intersect(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit> {
+        // Moving the ray the opposite way is equivalent, and leaves distances along it unchanged
+        let offset = self.velocity * ray.time;
+        let ray = Ray::new_at_time(ray.origin - offset, ray.direction, ray.time);
+        self.shape.intersect(&ray, t_min, t_max)
+    }
+
+    fn bounds(&self) -> Aabb {
+        let bounds = self.shape.bounds();
+        let end = Aabb::new(bounds.min + self.velocity, bounds.max + self.velocity);
+        bounds.union(&end)
+    }
+
+    fn material(&self) -> &Material {
+        self.shape.material()
+    }
+}
@@ -0,0 +1,118 @@
+use std::fs;
+use crate::cgmath::{InnerSpace, Vector3, Zero};
+use crate::scene::mesh::Triangle;
+
+/// A face's corner, as indices into the file's vertex and (optional) normal lists
+#[derive(Clone, Copy)]
+struct Corner {
+    vertex: usize,
+    normal: Option<usize>,
+}
+
+/// Loads the triangles from a Wavefront OBJ file.
+///
+/// Only geometry is read (`v`, `vn` and `f` statements); texture coordinates, groups and materials
+/// are ignored. Polygons with more than three vertices are triangulated as a fan. Face corners
+/// lacking a normal get a smooth normal, averaged from the faces which share that vertex.
+///
+/// On failure, returns a message describing the problem.
+pub fn load(filepath: &str) -> Result<Vec<Triangle>, String> {
+    let string = fs::read_to_string(filepath)
+        .map_err(|e| format!("could not read obj file {}: {}", filepath, e))?;
+    parse(&string).map_err(|message| format!("could not parse obj file {}: {}", filepath, message))
+}
+
+/// Makes triangles from the contents of an OBJ file.
+pub fn parse(string: &str) -> Result<Vec<Triangle>, String> {
+
+    let mut vertices = Vec::<Vector3<f64>>::new();
+    let mut normals = Vec::<Vector3<f64>>::new();
+    let mut faces = Vec::<[Corner; 3]>::new();
+
+    for (line_index, line) in string.lines().enumerate() {
+        let line_error = || format!("line {}: invalid statement '{}'", line_index + 1, line.trim());
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => vertices.push(parse_vec3(&mut tokens).ok_or_else(line_error)?),
+            Some("vn") => normals.push(parse_vec3(&mut tokens).ok_or_else(line_error)?),
+            Some("f") => {
+                let mut corners = Vec::<Corner>::new();
+                for token in tokens {
+                    let corner = parse_corner(token, vertices.len(), normals.len()).ok_or_else(line_error)?;
+                    corners.push(corner);
+                }
+                if corners.len() < 3 {
+                    return Err(line_error());
+                }
+                for i in 1..(corners.len() - 1) {
+                    faces.push([corners[0], corners[i], corners[i + 1]]);
+                }
+            },
+            _ => continue
+        }
+    }
+
+    // Accumulate face normals per vertex, weighted by face area (ie, the cross product's magnitude)
+    let mut smooth_normals = vec![Vector3::<f64>::zero(); vertices.len()];
+    for face in &faces {
+        let a = vertices[face[0].vertex];
+        let b = vertices[face[1].vertex];
+        let c = vertices[face[2].vertex];
+        let weighted_normal = (b - a).cross(c - a);
+        for corner in face {
+            smooth_normals[corner.vertex] += weighted_normal;
+        }
+    }
+
+    let mut triangles = Vec::<Triangle>::with_capacity(faces.len());
+    for face in &faces {
+        let a = vertices[face[0].vertex];
+        let b = vertices[face[1].vertex];
+        let c = vertices[face[2].vertex];
+        let mut triangle = Triangle::new_flat(a, b, c);
+        if triangle.face_normal().x.is_nan() {
+            continue; // degenerate
+        }
+        for (i, corner) in face.iter().enumerate() {
+            let normal = match corner.normal {
+                Some(index) => normals[index],
+                None => smooth_normals[corner.vertex]
+            };
+            if normal.magnitude2() > 0.0 {
+                triangle.normals[i] = normal.normalize();
+            }
+        }
+        triangles.push(triangle);
+    }
+    Ok(triangles)
+}
+
+fn parse_vec3<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Option<Vector3<f64>> {
+    let x = tokens.next()?.parse::<f64>().ok()?;
+    let y = tokens.next()?.parse::<f64>().ok()?;
+    let z = tokens.next()?.parse::<f64>().ok()?;
+    Some(Vector3::new(x, y, z))
+}
+
+/// Parses a face corner of the form `v`, `v/vt`, `v//vn` or `v/vt/vn`
+fn parse_corner(token: &str, num_vertices: usize, num_normals: usize) -> Option<Corner> {
+    let mut parts = token.split('/');
+    let vertex = parse_index(parts.next()?, num_vertices)?;
+    let _ = parts.next(); // texture coordinate index, unused
+    let normal = match parts.next() {
+        Some(part) if !part.is_empty() => Some(parse_index(part, num_normals)?),
+        _ => None
+    };
+    Some(Corner { vertex, normal })
+}
+
+/// Converts an OBJ index (1-based, or negative meaning relative to the end of the list)
+/// to a zero-based index
+fn parse_index(string: &str, count: usize) -> Option<usize> {
+    let index = string.parse::<i64>().ok()?;
+    let index = if index < 0 { count as i64 + index } else { index - 1 };
+    if index < 0 || index >= count as i64 {
+        return None;
+    }
+    Some(index as usize)
+}
@@ -1,20 +1,28 @@
-/// Logic for rendering a `Scene` to a `Canvas`
-///
-/// This is a reasonably faithful translation of the pseudo-code from the book
-/// ["Computer Graphics from Scratch"](https://gabrielgambetta.com/computer-graphics-from-scratch/)
-/// by Gabriel Gambetta.
+//! Logic for rendering a `Scene` to a `Canvas`
+//!
+//! This is a reasonably faithful translation of the pseudo-code from the book
+//! ["Computer Graphics from Scratch"](https://gabrielgambetta.com/computer-graphics-from-scratch/)
+//! by Gabriel Gambetta.
 
 use std::sync::{Arc, RwLock};
 use std::thread;
-use crate::cgmath::{InnerSpace, Vector3, Rotation};
+use crate::cgmath::{InnerSpace, Quaternion, Vector3, Rotation};
 use crate::base::color::Color;
-use crate::scene::scene::{Light, Scene, Specs, Sphere};
+use crate::scene::scene::{Light, SamplePattern, Scene, Specs};
+use crate::scene::bvh::Bvh;
+use crate::scene::camera::{Projection, StereoLayout};
+use crate::scene::shape::{Hit, Ray};
 use crate::canvas::canvas::Canvas;
-use crate::canvas::u8_canvas::U8Canvas;
-use crate::util::maths;
+use crate::canvas::float_canvas::FloatCanvas;
+use crate::util::{maths, random};
 
 const EPSILON: f64 = 0.001;
 const RECURSION_DEPTH: usize = 3;
+const TRANSMISSION_DEPTH: usize = 16;
+
+// The `render_to_canvas_*` functions are for display canvases (eg, `U8Canvas`, console canvas), and
+// apply the scene's tone mapping and output transfer. The `render_to_float_canvas*` functions leave
+// the values as-is (ie, as linear radiance).
 
 pub fn render_to_canvas_ranged(
         scene: &Arc<RwLock<Scene>>,
@@ -24,40 +32,96 @@ pub fn render_to_canvas_ranged(
         full_canvas_height: usize) {
 
     let scene = scene.read().unwrap();
+    let bvh = Bvh::from_shapes(&scene.objects);
+    render_rows(&scene, &bvh, sub_canvas, full_canvas_row_start, full_canvas_row_end, full_canvas_height, true);
+}
+
+fn render_rows(
+        scene: &Scene,
+        bvh: &Bvh,
+        sub_canvas: &mut dyn Canvas<Color>,
+        full_canvas_row_start: usize,
+        full_canvas_row_end: usize,
+        full_canvas_height: usize,
+        display: bool) {
 
     let full_canvas_num_cols = sub_canvas.get_width() as f64;
     let full_canvas_num_rows = full_canvas_height as f64;
-    let canvas_width = scene.specs.canvas_width;  // rem, scene space (not data grid values)
-    let canvas_height = scene.specs.canvas_height;
-    let canvas_width_half = scene.specs.canvas_width * 0.5;
-    let canvas_height_half = scene.specs.canvas_width * 0.5;
 
-    let mut viewport_width = scene.specs.viewport_width;
-    viewport_width *= full_canvas_num_cols / full_canvas_num_rows; // Adjust for canvas grid aspect ratio
-    viewport_width *= scene.specs.pixel_ar; // Adjust for pixel aspect ratio (viz., for terminal output)
-    let viewport_height = scene.specs.viewport_height;
+    // In stereo, `view` is that of the left eye and `right_view` that of the right,
+    // each covering its part of the canvas
+    let stereo = scene.specs.stereo;
+    let layout = stereo.map(|stereo| stereo.layout);
+    let aspect_ratio = full_canvas_num_cols / full_canvas_num_rows;
+    let eye_aspect_ratio = match layout {
+        Some(StereoLayout::SideBySide) => aspect_ratio * 0.5,
+        Some(StereoLayout::TopBottom) => aspect_ratio * 2.0,
+        Some(StereoLayout::Anaglyph) | None => aspect_ratio
+    };
+    let eye_offset = stereo.map_or(0.0, |stereo| stereo.eye_separation * 0.5);
+    let view = View::new(&scene.specs, eye_aspect_ratio, -eye_offset);
+    let right_view = View::new(&scene.specs, eye_aspect_ratio, eye_offset);
+    let trace = |view: &View, u: f64, v: f64, time: f64| match view.ray(u, v, time) {
+        Some(ray) => trace_ray(&ray, view.t_min, f64::INFINITY, scene, bvh, &[], Depth::INITIAL),
+        None => scene.specs.background_color
+    };
+
+    // Kept within the frame, which is as far as moving objects' bounds extend
+    let shutter_open = maths::clamp(scene.specs.shutter_open, 0.0, 1.0);
+    let shutter_close = maths::clamp(scene.specs.shutter_close, shutter_open, 1.0);
+
+    // One sample per sub-pixel cell
+    let cells = sample_cells(scene.specs.samples_per_pixel);
+    let num_samples = cells.len() as f64;
 
     for iy in full_canvas_row_start..full_canvas_row_end {
 
         let full_canvas_iy = iy - full_canvas_row_start;
 
-        let y = maths::map(iy as f64,
-           0.0, full_canvas_num_rows, canvas_height_half, -canvas_height_half);
-        let y = y * (viewport_height / canvas_height);
-
         for ix in 0..sub_canvas.get_width() {
 
-            let x = maths::map(ix as f64,
-                0.0, full_canvas_num_cols, -canvas_width_half, canvas_width_half);
-            let x = x * (viewport_width / canvas_width);
+            // Sum of the samples' colors (unclamped)
+            let (mut r, mut g, mut b) = (0.0, 0.0, 0.0);
 
-            let d = canvas_to_viewport(x, y, &scene.specs);
-            let quat = scene.specs.camera_orientation.clone();
-            let d = quat.rotate_vector(d);
+            for cell in &cells {
 
-            let o = scene.specs.camera_pos;
-            let color = trace_ray(o, d, 1.0, f64::INFINITY, &scene, -1, RECURSION_DEPTH);
+                // Position within the cell, and then within the pixel, in the range [0, 1)
+                let (offset_x, offset_y) = match scene.specs.sample_pattern {
+                    SamplePattern::Grid => (0.5, 0.5),
+                    SamplePattern::Jittered => (random::next_f64(), random::next_f64())
+                };
+                let offset_x = cell.x + offset_x * cell.width;
+                let offset_y = cell.y + offset_y * cell.height;
 
+                let u = (ix as f64 + offset_x) / full_canvas_num_cols;
+                let v = (iy as f64 + offset_y) / full_canvas_num_rows;
+                let time = if shutter_close > shutter_open {
+                    random::range(shutter_open, shutter_close)
+                } else {
+                    shutter_open
+                };
+                let color = match layout {
+                    None => trace(&view, u, v, time),
+                    Some(StereoLayout::SideBySide) if u < 0.5 => trace(&view, u * 2.0, v, time),
+                    Some(StereoLayout::SideBySide) => trace(&right_view, u * 2.0 - 1.0, v, time),
+                    Some(StereoLayout::TopBottom) if v < 0.5 => trace(&view, u, v * 2.0, time),
+                    Some(StereoLayout::TopBottom) => trace(&right_view, u, v * 2.0 - 1.0, time),
+                    Some(StereoLayout::Anaglyph) => {
+                        let left = trace(&view, u, v, time);
+                        let right = trace(&right_view, u, v, time);
+                        Color::new(left.r, right.g, right.b)
+                    }
+                };
+                r += color.r;
+                g += color.g;
+                b += color.b;
+            }
+
+            let mut color = Color::new(r / num_samples, g / num_samples, b / num_samples);
+            if display {
+                color = scene.specs.tone_mapping.apply(color, scene.specs.exposure);
+                color = scene.specs.output_transfer.encode(color);
+            }
             sub_canvas.set_value(ix, full_canvas_iy, &color);
         }
     }
@@ -76,13 +140,46 @@ pub fn render_to_canvas_all_mt(
         canvas: &mut dyn Canvas<Color>,
         worker_count: usize) {
 
+    render_mt(scene, canvas, worker_count, true);
+}
+
+/// Renders without tone mapping, so that the canvas holds the scene's radiance values
+pub fn render_to_float_canvas(
+        scene: &Arc<RwLock<Scene>>,
+        canvas: &mut FloatCanvas) {
+
+    let scene = scene.read().unwrap();
+    let bvh = Bvh::from_shapes(&scene.objects);
+    let height = canvas.get_height();
+    render_rows(&scene, &bvh, canvas, 0, height, height, false);
+}
+
+/// Multithreaded version of `render_to_float_canvas`
+pub fn render_to_float_canvas_mt(
+        scene: &Arc<RwLock<Scene>>,
+        canvas: &mut FloatCanvas,
+        worker_count: usize) {
+
+    render_mt(scene, canvas, worker_count, false);
+}
+
+fn render_mt(
+        scene: &Arc<RwLock<Scene>>,
+        canvas: &mut dyn Canvas<Color>,
+        worker_count: usize,
+        display: bool) {
+
     let canvas_full_height = canvas.get_height();
     let mut handles = Vec::new();
 
+    // Build the acceleration structure once, to be shared by all the workers
+    let bvh = Arc::new(Bvh::from_shapes(&scene.read().unwrap().objects));
+
     for i in 0..worker_count {
 
-        // make arc clone
+        // make arc clones
         let scene = Arc::clone(scene);
+        let bvh = Arc::clone(&bvh);
 
         // make "subcanvas"
         let y_span = canvas.get_height() / worker_count;
@@ -92,11 +189,12 @@ pub fn render_to_canvas_all_mt(
             y_end = canvas.get_height();
         }
         let sub_canvas_height = y_end - y_start;
-        let mut sub_canvas = U8Canvas::new(canvas.get_width(), sub_canvas_height);
+        let mut sub_canvas = FloatCanvas::new(canvas.get_width(), sub_canvas_height);
 
         // make thread and do work
         let handle = thread::spawn(move || {
-            render_to_canvas_ranged(&scene, &mut sub_canvas, y_start, y_end, canvas_full_height);
+            let scene = scene.read().unwrap();
+            render_rows(&scene, &bvh, &mut sub_canvas, y_start, y_end, canvas_full_height, display);
             (y_start, sub_canvas)
         });
 
@@ -107,7 +205,7 @@ pub fn render_to_canvas_all_mt(
     // Copy the sub_canvas data into the mut main canvas
     for handle in handles {
         let (y_start, sub_canvas) = handle.join().unwrap();
-        for y in y_start..(y_start + &sub_canvas.get_height()) {
+        for y in y_start..(y_start + sub_canvas.get_height()) {
             for x in 0..sub_canvas.get_width() {
                 let sub_canvas_y = y - y_start;
                 let color = sub_canvas.get_value(x, sub_canvas_y);
@@ -117,158 +215,256 @@ pub fn render_to_canvas_all_mt(
     }
 }
 
-/// Don't understand how the canvas width and height properties are useful, but
-fn canvas_to_viewport(x: f64, y: f64, specs: &Specs) -> Vector3<f64> {
-    let x = x * specs.viewport_width / specs.canvas_width;
-    let y = y * specs.viewport_height / specs.canvas_height;
-    Vector3::<f64>::from([x, y, specs.viewport_distance])
+/// Generates primary rays, per the camera and viewport values in `Specs`
+struct View {
+    projection: Projection,
+    position: Vector3<f64>,
+    orientation: Quaternion<f64>,
+    velocity: Vector3<f64>,
+    /// Viewport dimensions and distance from the camera, in world units
+    width: f64,
+    height: f64,
+    distance: f64,
+    /// Closest distance along a ray at which objects are visible
+    t_min: f64,
+    /// Lens radius, and distance to the plane of focus (see `Camera`)
+    aperture: f64,
+    focus_distance: f64,
+    /// For stereo, the eye's offset to the camera's right (negative for the left eye),
+    /// and the distance at which the eyes' views line up (see `Stereo`)
+    eye_offset: f64,
+    convergence: f64,
 }
 
-/// Returns the two 'distances' on a ray where it intersects a sphere.
-fn intersect_ray_sphere(
-        origin: Vector3<f64>,
-        direction: Vector3<f64>,
-        sphere: &Sphere) -> (f64, f64) {
+impl View {
 
-    let r = sphere.radius;
-    let c0 = origin - &sphere.center;
-    let a = direction.magnitude2(); // ie, d dot d
-    let b = 2.0 * &c0.dot(direction.clone());
-    let c = &c0.dot(c0.clone()) - (r * r);
-
-    let discriminant = b * b  -  4.0 * a * c;
-    if discriminant < 0.0 {
-        return (f64::NEG_INFINITY, f64::NEG_INFINITY);
+    /// `aspect_ratio` is that of the view's part of the canvas grid (columns / rows).
+    /// `eye_offset` is 0.0 unless rendering in stereo.
+    fn new(specs: &Specs, aspect_ratio: f64, eye_offset: f64) -> View {
+        let convergence = specs.stereo.map_or(1.0, |stereo| stereo.convergence);
+        let mut view = match &specs.camera {
+            Some(camera) => {
+                let height = 2.0 * (camera.fov.to_radians() * 0.5).tan();
+                View {
+                    projection: specs.projection,
+                    position: camera.position,
+                    orientation: camera.orientation(),
+                    velocity: specs.camera_velocity,
+                    width: height * aspect_ratio * specs.pixel_ar,
+                    height,
+                    distance: 1.0,
+                    t_min: EPSILON,
+                    aperture: camera.aperture,
+                    focus_distance: camera.focus_distance,
+                    eye_offset,
+                    convergence,
+                }
+            },
+            None => {
+                // Per the book, the canvas dimensions (in world units) scale the viewport dimensions;
+                // the canvas height's scale is relative to the canvas width
+                let viewport_width = specs.viewport_width * aspect_ratio * specs.pixel_ar;
+                let canvas_height_ratio = specs.viewport_height / specs.canvas_height;
+                View {
+                    projection: specs.projection,
+                    position: specs.camera_pos,
+                    orientation: specs.camera_orientation,
+                    velocity: specs.camera_velocity,
+                    width: viewport_width * specs.viewport_width / specs.canvas_width,
+                    height: specs.canvas_width * canvas_height_ratio * canvas_height_ratio,
+                    distance: specs.viewport_distance,
+                    // Objects between the camera and the viewport are not visible
+                    t_min: 1.0,
+                    aperture: 0.0,
+                    focus_distance: 1.0,
+                    eye_offset,
+                    convergence,
+                }
+            }
+        };
+        match specs.projection {
+            Projection::Perspective => {},
+            // The view plane is the viewport, with its size given directly
+            Projection::Orthographic { height } => {
+                view.width = height * aspect_ratio * specs.pixel_ar;
+                view.height = height;
+                view.t_min = 0.0;
+            },
+            // Rays go out in all directions from a single point, so there is no viewport or lens.
+            // Width and height are only kept for their proportions.
+            Projection::Equirectangular | Projection::Fisheye { .. } => {
+                view.width = aspect_ratio * specs.pixel_ar;
+                view.height = 1.0;
+                view.t_min = EPSILON;
+                view.aperture = 0.0;
+            }
+        }
+        view
     }
 
-    let t1 = (-b + discriminant.sqrt()) / (2.0 * a);
-    let t2 = (-b - discriminant.sqrt()) / (2.0 * a);
-    (t1, t2)
+    /// Returns the ray through the point `u`, `v` on the canvas,
+    /// where `u` goes from 0.0 to 1.0 left to right, and `v` from 0.0 to 1.0 top to bottom.
+    /// With an aperture, the ray starts from a random point on the lens.
+    /// Returns `None` for points outside of a fisheye's image circle.
+    fn ray(&self, u: f64, v: f64, time: f64) -> Option<Ray> {
+        let x = (u - 0.5) * self.width;
+        let y = (0.5 - v) * self.height;
+
+        // In camera space
+        let origin = Vector3::new(0.0, 0.0, 0.0);
+        let (mut o, mut d) = match self.projection {
+            Projection::Perspective => (origin, Vector3::new(x, y, self.distance)),
+            Projection::Orthographic { .. } => (Vector3::new(x, y, 0.0), Vector3::new(0.0, 0.0, 1.0)),
+            Projection::Equirectangular => {
+                let longitude = (u - 0.5) * std::f64::consts::TAU;
+                let latitude = (0.5 - v) * std::f64::consts::PI;
+                let d = Vector3::new(
+                    latitude.cos() * longitude.sin(), latitude.sin(), latitude.cos() * longitude.cos());
+                (origin, d)
+            },
+            Projection::Fisheye { fov } => {
+                // Distance from the center, where the image circle has a radius of 1.0
+                let scale = 2.0 / self.width.min(self.height);
+                let (x, y) = (x * scale, y * scale);
+                let r = (x * x + y * y).sqrt();
+                if r > 1.0 {
+                    return None;
+                }
+                let theta = r * (fov * 0.5).to_radians();
+                let phi = y.atan2(x);
+                (origin, Vector3::new(theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos()))
+            }
+        };
+
+        // Stereo: the eye is offset to the side, and its rays are aimed to meet those from the camera's
+        // position at the convergence distance (ie, an off-axis, rather than toed-in, projection).
+        // Panoramas are instead offset perpendicular to each ray, so that all directions get depth.
+        if self.eye_offset != 0.0 {
+            // The direction keeps its z for the planar projections, since `t_min` is relative to it
+            let (converge_point, scale) = match self.projection {
+                Projection::Perspective | Projection::Orthographic { .. } =>
+                    (o + d * (self.convergence / d.z), d.z / self.convergence),
+                Projection::Equirectangular | Projection::Fisheye { .. } =>
+                    (o + d.normalize() * self.convergence, 1.0)
+            };
+            o += match self.projection {
+                Projection::Equirectangular => Vector3::new(d.z, 0.0, -d.x).normalize() * self.eye_offset,
+                _ => Vector3::new(self.eye_offset, 0.0, 0.0)
+            };
+            d = (converge_point - o) * scale;
+        }
+
+        // Thin lens: rays from anywhere on the lens converge on the plane of focus
+        if self.aperture > 0.0 {
+            let focus_point = o + d * (self.focus_distance / d.z);
+            let r = self.aperture * random::next_f64().sqrt();
+            let theta = std::f64::consts::TAU * random::next_f64();
+            o += Vector3::new(r * theta.cos(), r * theta.sin(), 0.0);
+            d = focus_point - o;
+        }
+
+        let position = self.position + self.velocity * time;
+        Some(Ray::new_at_time(position + self.orientation.rotate_vector(o), self.orientation.rotate_vector(d), time))
+    }
 }
 
-/// Returns sphere index and closest_t
-fn get_closest_ray_sphere_intersection(
-        origin: Vector3<f64>,
-        direction: Vector3<f64>,
+/// Returns the closest intersection, and the index of the object intersected
+fn get_closest_intersection(
+        ray: &Ray,
         t_min:f64,
         t_max: f64,
-        spheres: &Vec<Sphere>,
-        sphere_ignore_index: i32) -> Option<(f64, usize)> {
+        scene: &Scene,
+        bvh: &Bvh) -> Option<(Hit, usize)> {
 
-    let mut result: Option<(f64, usize)> = None;
+    bvh.closest_hit(ray, t_min, t_max, |i, t_max| scene.objects[i].intersect(ray, t_min, t_max))
+}
 
-    let mut closest_t  = f64::INFINITY;
-    for (i, sphere) in spheres.iter().enumerate() {
-        if i as i32 == sphere_ignore_index {
-            continue;
-        }
-        let (t1, t2) = intersect_ray_sphere(origin, direction, &sphere);
-        if maths::contains(t1, t_min, t_max) && t1 < closest_t {
-            closest_t = t1;
-            result = Some((t1, i));
-        }
-        if maths::contains(t2, t_min, t_max) && t2 < closest_t {
-            closest_t = t2;
-            result = Some((t2, i));
-        }
-    }
-    result
-    // todo return borrowed sphere reference rather than index?
+/// Number of further bounces allowed, per kind of secondary ray
+#[derive(Clone, Copy)]
+struct Depth {
+    /// Reflections, including the reflected part at transparent surfaces
+    reflection: usize,
+    /// Rays continuing through transparent surfaces. Counted separately, so that looking through
+    /// several transparent objects doesn't use up the reflections.
+    transmission: usize,
+}
+
+impl Depth {
+    const INITIAL: Depth = Depth { reflection: RECURSION_DEPTH, transmission: TRANSMISSION_DEPTH };
 }
 
+/// `inside` is the indices of the transparent objects which the ray is inside of, innermost last
 fn trace_ray(
-    origin: Vector3<f64>,
-    direction: Vector3<f64>,
+    ray: &Ray,
     distance_min: f64,
     distance_max: f64,
     scene: &Scene,
-    ignore_sphere_index: i32,
-    recursion_depth: usize) -> Color {
-
-    let mut color;
+    bvh: &Bvh,
+    inside: &[usize],
+    depth: Depth) -> Color {
 
     // Compute local color
-    let option = get_closest_ray_sphere_intersection(origin, direction, distance_min, distance_max, &scene.spheres, ignore_sphere_index);
-    if option.is_none() {
-        color = scene.specs.background_color.clone();
-        return color;
-    }
-    let (t1, sphere_index) = option.unwrap();
-    let sphere = &scene.spheres[sphere_index];
-    let p = origin + (direction * t1);
-    let mut n = p - sphere.center;
-    n = n / n.magnitude();
-    let neg_d = direction * -1.0;
-    let intensity = compute_lighting(p, n, neg_d, sphere.specular, &scene);
-    color = sphere.color * intensity;
+    let option = get_closest_intersection(ray, distance_min, distance_max, scene, bvh);
+    let (hit, index) = match option {
+        None => return scene.specs.background_color,
+        Some(value) => value
+    };
+    let material = scene.objects[index].material();
+    let p = ray.at(hit.t);
+    // Shade using the side of the surface facing the ray
+    let mut n = hit.normal;
+    if n.dot(ray.direction) > 0.0 {
+        n = -n;
+    }
+
+    // A ray leaving a transparent object was already shaded where it entered the object,
+    // so the surface it leaves through only bends (and partly reflects) it
+    if inside.last() == Some(&index) {
+        let outside = &inside[..inside.len() - 1];
+        return trace_boundary(
+            ray, p, n, material.ior, medium_ior(outside, scene), inside, outside, scene, bvh, depth);
+    }
+
+    let neg_d = ray.direction * -1.0;
+    let lighting = compute_lighting(p, n, neg_d, material.specular, ray.time, scene, bvh);
+    let mut color = material.color.color_at(&hit) * lighting;
 
     // Reflected color
-    if sphere.reflective > 0.0 && recursion_depth > 0 {
-        let neg_d = direction * -1.0;
+    if material.reflective > 0.0 && depth.reflection > 0 {
         let r2 = reflect_ray(neg_d, n);
         // Recursion action
-        let reflected_color = trace_ray(p, r2, EPSILON, f64::INFINITY, scene, -1, recursion_depth - 1);
-        color = Color::lerp(color, reflected_color, sphere.reflective);
+        let reflected_color = trace_ray(&Ray::new_at_time(p, r2, ray.time), EPSILON, f64::INFINITY, scene, bvh, inside, Depth { reflection: depth.reflection - 1, ..depth });
+        color = Color::lerp(color, reflected_color, material.reflective);
     }
 
-    // Transparency
-    if sphere.transparency > 0.0 {
-        let trans_color
-            = trace_ray(p, direction, EPSILON, distance_max, scene, sphere_index as i32, 3);
-        color = Color::lerp(color, trans_color, sphere.transparency);
+    // Transparency, as the Fresnel-weighted mix of the reflected and refracted rays
+    if material.transparency > 0.0 {
+        let mut inside_refracted = inside.to_vec();
+        inside_refracted.push(index);
+        let trans_color = trace_boundary(
+            ray, p, n, medium_ior(inside, scene), material.ior, inside, &inside_refracted, scene, bvh, depth);
+        color = Color::lerp(color, trans_color, material.transparency);
     }
 
-    return color
+    color
 }
 
+/// Returns the light arriving at point `p` at the given time, per color channel
 fn compute_lighting(
         p: Vector3<f64>,
         n: Vector3<f64>,
         v: Vector3<f64>,
         s: f64,
-        scene: &Scene) -> f64 {
-
-    let mut final_intensity = 0.0;
+        time: f64,
+        scene: &Scene,
+        bvh: &Bvh) -> Color {
 
-    for light in &scene.lights {
+    // Light arriving from direction `l` (scaled by `factor`), after diffuse, specular and shadows.
+    // `t_max` is the distance along `l` to the light (ie, 1.0 for lights which have a position)
+    let shade = |l: Vector3<f64>, t_max: f64, factor: f64| -> Color {
 
         let mut light_intensity:f64 = 0.0;
 
-        let l: Vector3<f64>;
-        let t_max;
-        let factor: f64;
-
-        match light {
-            Light::Ambient { intensity } => {
-                final_intensity += intensity;
-                continue;
-            },
-            Light::Point{ intensity, position} => {
-                l = position - p;
-                t_max = 1.0;
-                factor = *intensity;
-            },
-            Light::Directional { intensity, direction } => {
-                l = direction.clone();
-                t_max = f64::INFINITY;
-                factor = *intensity;
-            }
-        }
-
-        // Get shadow attenuation factor
-        let shadow_attenuation: f64;
-        let option = get_closest_ray_sphere_intersection(p, l, EPSILON, t_max, &scene.spheres, -1);
-        match option {
-            Some((distance, index)) => {
-                // Attenuate by the object's amount of opacity
-                shadow_attenuation = 1.0 - scene.spheres[index].transparency;
-            },
-            _ => shadow_attenuation = 0.0
-        }
-         if shadow_attenuation == 1.0 {
-            continue;
-        }
-
         // Diffuse
         let n_dot_l = n.dot(l);
         if n_dot_l > 0.0 {
@@ -279,21 +475,665 @@ fn compute_lighting(
         // Specular
         if s > 0.0 {
             let r = n * (2.0 * n.dot(l));
-            let r = &r - &l;
+            let r = r - l;
             let r_dot_v = r.dot(v);
             if r_dot_v > 0.0 {
                 light_intensity += factor * (r_dot_v / (r.magnitude() * v.magnitude())).powf(s);
             }
         }
 
-        // Apply shadow attenuation
-        light_intensity *= 1.0 - shadow_attenuation;
+        if light_intensity <= 0.0 {
+            return Color::new_black(); // No need for shadow rays
+        }
+
+        // Apply shadows
+        let shadow_ray = Ray::new_at_time(p, l, time);
+        match shadow_transmittance(&shadow_ray, t_max, scene, bvh) {
+            Some(transmittance) => transmittance * light_intensity,
+            None => Color::new_black()
+        }
+    };
+
+    let mut final_color = Color::new_black();
+
+    for light in &scene.lights {
+
+        let light_color = match light {
+            Light::Ambient { intensity, color } => {
+                *color * *intensity
+            },
+            Light::Point { intensity, color, position, attenuation, range, velocity } => {
+                let l = position + velocity * time - p;
+                let distance = l.magnitude();
+                if range.is_some_and(|range| distance > range) {
+                    continue; // Out of range, so no need for shadow rays
+                }
+                *color * shade(l, 1.0, intensity * attenuation.factor(distance))
+            },
+            Light::Directional { intensity, color, direction } => {
+                *color * shade(*direction, f64::INFINITY, *intensity)
+            },
+            Light::Spot { intensity, color, position, direction, inner_angle, outer_angle, attenuation, range, velocity } => {
+                let l = position + velocity * time - p;
+                let distance = l.magnitude();
+                if range.is_some_and(|range| distance > range) {
+                    continue; // Out of range, so no need for shadow rays
+                }
+                let factor = intensity
+                    * spot_falloff(l, *direction, *inner_angle, *outer_angle)
+                    * attenuation.factor(distance);
+                if factor == 0.0 {
+                    continue; // Outside the cone, so no need for shadow rays
+                }
+                *color * shade(l, 1.0, factor)
+            },
+            Light::Rect { intensity, color, position, edge_u, edge_v, velocity } => {
+                let position = position + velocity * time;
+                // Samples are jittered within a grid of cells across the rectangle
+                let samples = stratified_samples(scene.specs.light_samples);
+                let factor = intensity / samples.len() as f64;
+                let sum = samples.iter()
+                    .map(|(u, v)| position + edge_u * (u - 0.5) + edge_v * (v - 0.5))
+                    .fold(Color::new_black(), |sum, point| sum + shade(point - p, 1.0, factor));
+                *color * sum
+            },
+            Light::Sphere { intensity, color, position, radius, velocity } => {
+                let position = position + velocity * time;
+                // As seen from `p`, the sphere is a disc facing `p`, so samples are taken across that disc
+                let (axis_a, axis_b) = perpendicular_axes(p - position);
+                let samples = stratified_samples(scene.specs.light_samples);
+                let factor = intensity / samples.len() as f64;
+                let sum = samples.iter()
+                    .map(|(u, v)| {
+                        // Uniformly distributed over the disc's area
+                        let r = radius * u.sqrt();
+                        let theta = std::f64::consts::TAU * v;
+                        position + (axis_a * theta.cos() + axis_b * theta.sin()) * r
+                    })
+                    .fold(Color::new_black(), |sum, point| sum + shade(point - p, 1.0, factor));
+                *color * sum
+            }
+        };
+
+        final_color = final_color + light_color;
+    }
+    final_color
+}
+
+/// Returns the fraction of light (per color channel) which reaches the end of the shadow ray,
+/// or `None` if the light is blocked entirely.
+///
+/// Every object between the point and the light contributes, so light passing through two glass
+/// objects is dimmer than through one. Each transparent object filters the light by its color
+/// where the ray passes through it.
+fn shadow_transmittance(shadow_ray: &Ray, t_max: f64, scene: &Scene, bvh: &Bvh) -> Option<Color> {
+    let mut transmittance = Color::new(1.0, 1.0, 1.0);
+
+    // Visits every object the ray passes through (once each, however many of its surfaces are crossed),
+    // stopping early at the first opaque one
+    let opaque_hit = bvh.any_hit(shadow_ray, EPSILON, t_max, |i, t_max| {
+        let object = &scene.objects[i];
+        let hit = object.intersect(shadow_ray, EPSILON, t_max)?;
+        let material = object.material();
+        if material.transparency <= 0.0 {
+            return Some(hit);
+        }
+        transmittance = transmittance * material.color.color_at(&hit) * material.transparency;
+        None
+    });
+
+    if opaque_hit.is_some() || transmittance == Color::new_black() {
+        None
+    } else {
+        Some(transmittance)
+    }
+}
+
+/// Returns `count` positions within the unit square, one per cell of `sample_cells`,
+/// randomly positioned within its cell
+fn stratified_samples(count: usize) -> Vec<(f64, f64)> {
+    sample_cells(count).iter()
+        .map(|cell| (cell.x + random::next_f64() * cell.width, cell.y + random::next_f64() * cell.height))
+        .collect()
+}
+
+/// A rectangle within the unit square
+struct Cell {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+/// Divides the unit square into `count` cells (at least one), as evenly as possible:
+/// rows of equal height, each divided into equal-width cells, with the row lengths differing by
+/// at most one. A square number makes a square grid.
+fn sample_cells(count: usize) -> Vec<Cell> {
+    let count = count.max(1);
+    let num_rows = ((count as f64).sqrt().round() as usize).max(1);
+    let height = 1.0 / num_rows as f64;
+    let mut cells = Vec::with_capacity(count);
+    for row in 0..num_rows {
+        let num_cols = count * (row + 1) / num_rows - count * row / num_rows;
+        let width = 1.0 / num_cols as f64;
+        for col in 0..num_cols {
+            cells.push(Cell { x: col as f64 * width, y: row as f64 * height, width, height });
+        }
+    }
+    cells
+}
+
+/// Returns two unit vectors which are perpendicular to `w` and to each other
+fn perpendicular_axes(w: Vector3<f64>) -> (Vector3<f64>, Vector3<f64>) {
+    let w = w.normalize();
+    // Start from whichever world axis is least aligned with `w`
+    let helper = if w.x.abs() < 0.9 { Vector3::unit_x() } else { Vector3::unit_y() };
+    let a = w.cross(helper).normalize();
+    let b = w.cross(a);
+    (a, b)
+}
+
+/// Returns the spot light's falloff factor (0.0 to 1.0), where `l` points from the lit point to the light
+fn spot_falloff(l: Vector3<f64>, direction: Vector3<f64>, inner_angle: f64, outer_angle: f64) -> f64 {
+    let cos = (-l).normalize().dot(direction.normalize());
+    let cos_inner = inner_angle.to_radians().cos();
+    let cos_outer = outer_angle.to_radians().cos();
+    maths::smoothstep(cos_outer, cos_inner, cos)
+}
+
+/// Returns the Fresnel-weighted mix of the rays reflected and refracted at point `p`, on the boundary
+/// between the medium the ray is in (index of refraction `n1`) and the one on the other side (`n2`).
+/// `n` faces against the ray. `inside` and `inside_refracted` are the transparent objects which the ray is
+/// inside of before and after crossing the boundary (see `trace_ray`). Either ray gets the background
+/// color once out of depth.
+#[allow(clippy::too_many_arguments)]
+fn trace_boundary(
+    ray: &Ray,
+    p: Vector3<f64>,
+    n: Vector3<f64>,
+    n1: f64,
+    n2: f64,
+    inside: &[usize],
+    inside_refracted: &[usize],
+    scene: &Scene,
+    bvh: &Bvh,
+    depth: Depth) -> Color {
+
+    let d = ray.direction.normalize();
+    let reflected = reflect_ray(-d, n);
+    let (refracted, reflectance) = match refract_ray(d, n, n1 / n2) {
+        Some(refracted) => {
+            // Schlick uses the angle on the side of the less dense medium
+            let cos = if n1 <= n2 { -n.dot(d) } else { -n.dot(refracted) };
+            (Some(refracted), schlick(cos, n1, n2))
+        },
+        None => (None, 1.0) // Total internal reflection
+    };
 
-        final_intensity += light_intensity;
+    let mut color = Color::new_black();
+    if reflectance > 0.0 {
+        let reflected_color = if depth.reflection > 0 {
+            let reflected_ray = Ray::new_at_time(p, reflected, ray.time);
+            trace_ray(&reflected_ray, EPSILON, f64::INFINITY, scene, bvh, inside, Depth { reflection: depth.reflection - 1, ..depth })
+        } else {
+            scene.specs.background_color
+        };
+        color = reflected_color * reflectance;
+    }
+    if let Some(refracted) = refracted {
+        let refracted_color = if depth.transmission > 0 {
+            let refracted_ray = Ray::new_at_time(p, refracted, ray.time);
+            trace_ray(&refracted_ray, EPSILON, f64::INFINITY, scene, bvh, inside_refracted, Depth { transmission: depth.transmission - 1, ..depth })
+        } else {
+            scene.specs.background_color
+        };
+        color = color + refracted_color * (1.0 - reflectance);
     }
-    final_intensity
+    color
+}
+
+/// Index of refraction of the innermost of the given objects, or of air (1.0) if there are none
+fn medium_ior(inside: &[usize], scene: &Scene) -> f64 {
+    inside.last().map_or(1.0, |i| scene.objects[*i].material().ior)
 }
 
 fn reflect_ray(r: Vector3<f64>, n: Vector3<f64>) -> Vector3<f64> {
     2.0 * n * n.dot(r)  -  r
 }
+
+/// Refracts the normalized direction `d` through a surface with normal `n` (facing against `d`),
+/// per Snell's law, where `eta` is the ratio of the indices of refraction (from / to).
+/// Returns `None` in the case of total internal reflection.
+fn refract_ray(d: Vector3<f64>, n: Vector3<f64>, eta: f64) -> Option<Vector3<f64>> {
+    let cos_i = -n.dot(d);
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+    if sin2_t > 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(d * eta + n * (eta * cos_i - cos_t))
+}
+
+/// Schlick's approximation of the Fresnel reflectance between media with indices `n1` and `n2`
+fn schlick(cos: f64, n1: f64, n2: f64) -> f64 {
+    if n1 == n2 {
+        return 0.0; // No interface, so nothing gets reflected
+    }
+    let r0 = ((n1 - n2) / (n1 + n2)).powi(2);
+    r0 + (1.0 - r0) * (1.0 - cos).powi(5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::camera::{Camera, Stereo};
+    use crate::scene::scene::{Attenuation, Sphere};
+    use crate::scene::shape::{Material, Shape};
+
+    const TOLERANCE: f64 = 1e-12;
+
+    #[test]
+    fn sample_cells_has_requested_count_and_covers_unit_square() {
+        for count in 1..=20 {
+            let cells = sample_cells(count);
+            assert_eq!(cells.len(), count);
+            let area: f64 = cells.iter().map(|cell| cell.width * cell.height).sum();
+            assert!((area - 1.0).abs() < TOLERANCE);
+            for cell in &cells {
+                assert!(cell.x >= 0.0 && cell.x + cell.width <= 1.0 + TOLERANCE);
+                assert!(cell.y >= 0.0 && cell.y + cell.height <= 1.0 + TOLERANCE);
+            }
+        }
+        assert_eq!(sample_cells(0).len(), 1);
+    }
+
+    #[test]
+    fn sample_cells_of_square_number_is_square_grid() {
+        let cells = sample_cells(9);
+        assert!(cells.iter().all(|cell| cell.width == 1.0 / 3.0 && cell.height == 1.0 / 3.0));
+    }
+
+    #[test]
+    fn refract_ray_total_internal_reflection() {
+        // From glass to air at 60 degrees, past the critical angle of ~41.8 degrees
+        let d = Vector3::new(60_f64.to_radians().sin(), -60_f64.to_radians().cos(), 0.0);
+        let n = Vector3::new(0.0, 1.0, 0.0);
+        assert!(refract_ray(d, n, 1.5 / 1.0).is_none());
+        // ...but not below it
+        let d = Vector3::new(30_f64.to_radians().sin(), -30_f64.to_radians().cos(), 0.0);
+        assert!(refract_ray(d, n, 1.5 / 1.0).is_some());
+    }
+
+    #[test]
+    fn refract_ray_follows_snells_law() {
+        let d = Vector3::new(45_f64.to_radians().sin(), -45_f64.to_radians().cos(), 0.0);
+        let n = Vector3::new(0.0, 1.0, 0.0);
+        let refracted = refract_ray(d, n, 1.0 / 1.5).unwrap();
+        assert!((refracted.magnitude() - 1.0).abs() < TOLERANCE);
+        assert!((refracted.x - 45_f64.to_radians().sin() / 1.5).abs() < TOLERANCE);
+        assert!(refracted.y < 0.0);
+    }
+
+    #[test]
+    fn refract_ray_at_ior_1_passes_straight_through() {
+        let d = Vector3::new(1.0, -2.0, 0.5).normalize();
+        let n = Vector3::new(0.0, 1.0, 0.0);
+        let refracted = refract_ray(d, n, 1.0).unwrap();
+        assert!((refracted - d).magnitude() < TOLERANCE);
+    }
+
+    #[test]
+    fn schlick_at_normal_incidence_is_r0() {
+        let r0 = ((1.0 - 1.5) / (1.0 + 1.5)) * ((1.0 - 1.5) / (1.0 + 1.5));
+        assert!((schlick(1.0, 1.0, 1.5) - r0).abs() < TOLERANCE);
+        assert!((schlick(1.0, 1.5, 1.0) - r0).abs() < TOLERANCE);
+        assert!((r0 - 0.04).abs() < TOLERANCE);
+    }
+
+    #[test]
+    fn schlick_at_grazing_angle_is_1() {
+        assert!((schlick(0.0, 1.0, 1.5) - 1.0).abs() < TOLERANCE);
+    }
+
+    #[test]
+    fn schlick_at_ior_1_reflects_nothing() {
+        assert_eq!(schlick(0.3, 1.0, 1.0), 0.0);
+    }
+
+    /// Light arriving at the origin, on a surface facing up (with no specular highlight)
+    fn lighting_at_origin(lights: Vec<Light>, objects: Vec<Box<dyn Shape>>) -> Color {
+        let scene = Scene { specs: Specs::new_with_defaults(), lights, objects };
+        let bvh = Bvh::from_shapes(&scene.objects);
+        let n = Vector3::new(0.0, 1.0, 0.0);
+        compute_lighting(Vector3::new(0.0, 0.0, 0.0), n, n, -1.0, 0.0, &scene, &bvh)
+    }
+
+    fn assert_gray(color: Color, expected: f64) {
+        for component in [color.r, color.g, color.b] {
+            assert!((component - expected).abs() < 1e-9, "expected {}, got {:?}", expected, color);
+        }
+    }
+
+    /// Direction from the origin at `degrees` from straight up
+    fn direction_at_angle(degrees: f64) -> Vector3<f64> {
+        Vector3::new(degrees.to_radians().sin(), degrees.to_radians().cos(), 0.0)
+    }
+
+    #[test]
+    fn spot_falloff_inside_between_and_outside_cones() {
+        let down = Vector3::new(0.0, -1.0, 0.0);
+        // Full inside the inner cone, none outside the outer one
+        assert_eq!(spot_falloff(direction_at_angle(0.0), down, 10.0, 20.0), 1.0);
+        assert_eq!(spot_falloff(direction_at_angle(9.0), down, 10.0, 20.0), 1.0);
+        assert_eq!(spot_falloff(direction_at_angle(21.0), down, 10.0, 20.0), 0.0);
+        assert_eq!(spot_falloff(direction_at_angle(90.0), down, 10.0, 20.0), 0.0);
+        // Smoothly decreasing in between
+        let falloffs = [11.0, 13.0, 15.0, 17.0, 19.0].map(|angle| spot_falloff(direction_at_angle(angle), down, 10.0, 20.0));
+        assert!(falloffs.iter().all(|falloff| *falloff > 0.0 && *falloff < 1.0), "{:?}", falloffs);
+        assert!(falloffs.windows(2).all(|pair| pair[0] > pair[1]), "{:?}", falloffs);
+        let expected = maths::smoothstep(20_f64.to_radians().cos(), 10_f64.to_radians().cos(), 15_f64.to_radians().cos());
+        assert!((falloffs[2] - expected).abs() < TOLERANCE);
+    }
+
+    #[test]
+    fn spot_falloff_with_equal_angles_has_hard_edge() {
+        let down = Vector3::new(0.0, -1.0, 0.0);
+        assert_eq!(spot_falloff(direction_at_angle(14.9), down, 15.0, 15.0), 1.0);
+        assert_eq!(spot_falloff(direction_at_angle(15.1), down, 15.0, 15.0), 0.0);
+    }
+
+    #[test]
+    fn spot_light_lights_only_within_its_cone() {
+        let spot = |x: f64| Light::Spot {
+            intensity: 1.0,
+            color: Color::new(1.0, 1.0, 1.0),
+            position: Vector3::new(x, 5.0, 0.0),
+            direction: Vector3::new(0.0, -1.0, 0.0),
+            inner_angle: 10.0,
+            outer_angle: 20.0,
+            attenuation: Attenuation::None,
+            range: None,
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+        };
+        assert_gray(lighting_at_origin(vec![spot(0.0)], vec![]), 1.0);
+        assert_gray(lighting_at_origin(vec![spot(5.0 * 30_f64.to_radians().tan())], vec![]), 0.0);
+        let between = lighting_at_origin(vec![spot(5.0 * 15_f64.to_radians().tan())], vec![]);
+        assert!(between.r > 0.0 && between.r < 15_f64.to_radians().cos(), "{:?}", between);
+    }
+
+    /// Opaque sphere which is big enough to act as a wall, blocking everything at x < 0 around y = 2
+    fn half_blocker() -> Box<dyn Shape> {
+        Box::new(Sphere { center: Vector3::new(-100.0, 2.0, 0.0), radius: 100.0, material: Material::new_with_defaults() })
+    }
+
+    #[test]
+    fn rect_light_casts_soft_shadows() {
+        let light = Light::Rect {
+            intensity: 1.0,
+            color: Color::new(1.0, 1.0, 1.0),
+            position: Vector3::new(0.0, 4.0, 0.0),
+            edge_u: Vector3::new(4.0, 0.0, 0.0),
+            edge_v: Vector3::new(0.0, 0.0, 4.0),
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+        };
+        let lit = lighting_at_origin(vec![light], vec![]);
+        assert!(lit.r > 0.5, "{:?}", lit);
+
+        // Penumbra: the blocker hides half of the light
+        let penumbra = lighting_at_origin(vec![light], vec![half_blocker()]);
+        let fraction = penumbra.r / lit.r;
+        assert!(fraction > 0.3 && fraction < 0.7, "{}", fraction);
+
+        // Umbra: the blocker hides all of the light
+        let blocker = Sphere { center: Vector3::new(0.0, 2.0, 0.0), radius: 1.5, material: Material::new_with_defaults() };
+        assert_gray(lighting_at_origin(vec![light], vec![Box::new(blocker)]), 0.0);
+    }
+
+    #[test]
+    fn sphere_light_casts_soft_shadows() {
+        let light = Light::Sphere {
+            intensity: 1.0,
+            color: Color::new(1.0, 1.0, 1.0),
+            position: Vector3::new(0.0, 4.0, 0.0),
+            radius: 2.0,
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+        };
+        let lit = lighting_at_origin(vec![light], vec![]);
+        let penumbra = lighting_at_origin(vec![light], vec![half_blocker()]);
+        let fraction = penumbra.r / lit.r;
+        assert!(fraction > 0.3 && fraction < 0.7, "{}", fraction);
+    }
+
+    fn point_light(distance: f64, attenuation: Attenuation, range: Option<f64>) -> Light {
+        Light::Point {
+            intensity: 2.0,
+            color: Color::new(1.0, 1.0, 1.0),
+            position: Vector3::new(0.0, distance, 0.0),
+            attenuation,
+            range,
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    #[test]
+    fn point_light_attenuation() {
+        assert_gray(lighting_at_origin(vec![point_light(4.0, Attenuation::None, None)], vec![]), 2.0);
+        assert_gray(lighting_at_origin(vec![point_light(4.0, Attenuation::InverseSquare, None)], vec![]), 2.0 / 16.0);
+        assert_gray(lighting_at_origin(vec![point_light(8.0, Attenuation::InverseSquare, None)], vec![]), 2.0 / 64.0);
+        let coefficients = Attenuation::Coefficients { constant: 1.0, linear: 0.5, quadratic: 0.25 };
+        assert_gray(lighting_at_origin(vec![point_light(4.0, coefficients, None)], vec![]), 2.0 / (1.0 + 2.0 + 4.0));
+    }
+
+    #[test]
+    fn point_light_has_no_effect_beyond_range() {
+        assert_gray(lighting_at_origin(vec![point_light(4.0, Attenuation::None, Some(5.0))], vec![]), 2.0);
+        assert_gray(lighting_at_origin(vec![point_light(4.0, Attenuation::None, Some(3.0))], vec![]), 0.0);
+        assert_gray(lighting_at_origin(vec![point_light(4.0, Attenuation::InverseSquare, Some(3.0))], vec![]), 0.0);
+    }
+
+    fn red_glass(y: f64) -> Box<dyn Shape> {
+        let material = Material { color: Color::new(1.0, 0.0, 0.0).into(), transparency: 0.5, ..Material::new_with_defaults() };
+        Box::new(Sphere { center: Vector3::new(0.0, y, 0.0), radius: 1.0, material })
+    }
+
+    #[test]
+    fn shadow_through_glass_is_tinted_and_cumulative() {
+        let light = point_light(10.0, Attenuation::None, None);
+        assert_gray(lighting_at_origin(vec![light], vec![]), 2.0);
+
+        // Red-tinted, and dimmed by the sphere's transparency
+        let one = lighting_at_origin(vec![light], vec![red_glass(5.0)]);
+        assert!((one.r - 1.0).abs() < 1e-9 && one.g == 0.0 && one.b == 0.0, "{:?}", one);
+
+        // Each sphere filters the light again
+        let two = lighting_at_origin(vec![light], vec![red_glass(3.0), red_glass(7.0)]);
+        assert!((two.r - 0.5).abs() < 1e-9 && two.g == 0.0 && two.b == 0.0, "{:?}", two);
+    }
+
+    #[test]
+    fn shadow_from_opaque_object_behind_glass_is_black() {
+        let light = point_light(10.0, Attenuation::None, None);
+        let opaque = Sphere { center: Vector3::new(0.0, 7.0, 0.0), radius: 1.0, material: Material::new_with_defaults() };
+        assert_gray(lighting_at_origin(vec![light], vec![red_glass(3.0), Box::new(opaque)]), 0.0);
+    }
+
+    /// Specs with a look-at camera at (1, 2, 3), looking along +z
+    fn camera_specs(fov: f64) -> Specs {
+        let mut specs = Specs::new_with_defaults();
+        specs.camera = Some(Camera::new(Vector3::new(1.0, 2.0, 3.0), Vector3::new(1.0, 2.0, 10.0), Vector3::new(0.0, 1.0, 0.0), fov));
+        specs
+    }
+
+    fn angle_between(a: Vector3<f64>, b: Vector3<f64>) -> f64 {
+        a.normalize().dot(b.normalize()).clamp(-1.0, 1.0).acos().to_degrees()
+    }
+
+    #[test]
+    fn look_at_camera_field_of_view() {
+        let forward = Vector3::new(0.0, 0.0, 1.0);
+        let view = View::new(&camera_specs(90.0), 1.0, 0.0);
+        let center = view.ray(0.5, 0.5, 0.0).unwrap();
+        assert!((center.origin - Vector3::new(1.0, 2.0, 3.0)).magnitude() < TOLERANCE);
+        assert!(angle_between(center.direction, forward) < 1e-6);
+        // The field of view is vertical, from the top edge to the bottom edge
+        let top = view.ray(0.5, 0.0, 0.0).unwrap();
+        assert!((angle_between(top.direction, forward) - 45.0).abs() < 1e-6);
+        assert!(top.direction.y > 0.0);
+        let right = view.ray(1.0, 0.5, 0.0).unwrap();
+        assert!((angle_between(right.direction, forward) - 45.0).abs() < 1e-6);
+        assert!(right.direction.x > 0.0);
+
+        let view = View::new(&camera_specs(60.0), 1.0, 0.0);
+        let bottom = view.ray(0.5, 1.0, 0.0).unwrap();
+        assert!((angle_between(bottom.direction, forward) - 30.0).abs() < 1e-6);
+        assert!(bottom.direction.y < 0.0);
+    }
+
+    #[test]
+    fn look_at_camera_width_follows_aspect_ratio() {
+        let view = View::new(&camera_specs(90.0), 2.0, 0.0);
+        let right = view.ray(1.0, 0.5, 0.0).unwrap();
+        // Twice as wide as tall, so the edge is at tan(45°) * 2 to the side
+        assert!((angle_between(right.direction, Vector3::new(0.0, 0.0, 1.0)) - 2_f64.atan().to_degrees()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn orthographic_rays_are_parallel_from_view_plane() {
+        let mut specs = camera_specs(60.0);
+        specs.projection = Projection::Orthographic { height: 4.0 };
+        let view = View::new(&specs, 1.5, 0.0);
+        for (u, v) in [(0.5, 0.5), (0.0, 0.0), (1.0, 1.0), (0.25, 0.75)] {
+            let ray = view.ray(u, v, 0.0).unwrap();
+            assert!(angle_between(ray.direction, Vector3::new(0.0, 0.0, 1.0)) < 1e-6, "{:?}", ray);
+        }
+        // The view plane is 4.0 tall, and 1.5 times as wide, centered on the camera
+        let top_left = view.ray(0.0, 0.0, 0.0).unwrap();
+        assert!((top_left.origin - Vector3::new(1.0 - 3.0, 2.0 + 2.0, 3.0)).magnitude() < TOLERANCE, "{:?}", top_left);
+        let bottom_right = view.ray(1.0, 1.0, 0.0).unwrap();
+        assert!((bottom_right.origin - Vector3::new(1.0 + 3.0, 2.0 - 2.0, 3.0)).magnitude() < TOLERANCE, "{:?}", bottom_right);
+        assert_eq!(view.t_min, 0.0);
+    }
+
+    /// Where the ray crosses the plane at the given z
+    fn point_at_z(ray: &Ray, z: f64) -> Vector3<f64> {
+        ray.origin + ray.direction * ((z - ray.origin.z) / ray.direction.z)
+    }
+
+    #[test]
+    fn thin_lens_rays_meet_at_focus_distance() {
+        let mut specs = camera_specs(60.0);
+        let camera = specs.camera.as_mut().unwrap();
+        camera.aperture = 0.5;
+        camera.focus_distance = 10.0;
+        let view = View::new(&specs, 1.0, 0.0);
+        let pinhole = View::new(&camera_specs(60.0), 1.0, 0.0).ray(0.3, 0.6, 0.0).unwrap();
+        let focus_point = point_at_z(&pinhole, 3.0 + 10.0);
+
+        let mut spread = 0.0_f64;
+        for _ in 0..100 {
+            let ray = view.ray(0.3, 0.6, 0.0).unwrap();
+            // Starts somewhere on the lens...
+            let lens_offset = ray.origin - Vector3::new(1.0, 2.0, 3.0);
+            assert!(lens_offset.z.abs() < TOLERANCE && lens_offset.magnitude() <= 0.5, "{:?}", ray);
+            // ...and passes through the same point on the plane of focus as the pinhole ray
+            assert!((point_at_z(&ray, 13.0) - focus_point).magnitude() < 1e-9, "{:?}", ray);
+            // Nearer than that, rays are spread out (ie, blurred)
+            spread = spread.max((point_at_z(&ray, 8.0) - point_at_z(&pinhole, 8.0)).magnitude());
+        }
+        assert!(spread > 0.05, "{}", spread);
+    }
+
+    fn assert_direction(view: &View, u: f64, v: f64, expected: Vector3<f64>) {
+        let ray = view.ray(u, v, 0.0).unwrap();
+        assert!((ray.origin - Vector3::new(1.0, 2.0, 3.0)).magnitude() < TOLERANCE, "{:?}", ray);
+        assert!(angle_between(ray.direction, expected) < 1e-6, "at {}, {}: expected {:?}, got {:?}", u, v, expected, ray.direction);
+    }
+
+    #[test]
+    fn equirectangular_maps_canvas_to_longitude_and_latitude() {
+        let mut specs = camera_specs(60.0);
+        specs.projection = Projection::Equirectangular;
+        let view = View::new(&specs, 2.0, 0.0);
+        assert_direction(&view, 0.5, 0.5, Vector3::new(0.0, 0.0, 1.0));
+        assert_direction(&view, 0.75, 0.5, Vector3::new(1.0, 0.0, 0.0));
+        assert_direction(&view, 0.25, 0.5, Vector3::new(-1.0, 0.0, 0.0));
+        assert_direction(&view, 0.0, 0.5, Vector3::new(0.0, 0.0, -1.0));
+        assert_direction(&view, 1.0, 0.5, Vector3::new(0.0, 0.0, -1.0));
+        assert_direction(&view, 0.5, 0.0, Vector3::new(0.0, 1.0, 0.0));
+        assert_direction(&view, 0.5, 1.0, Vector3::new(0.0, -1.0, 0.0));
+        assert_direction(&view, 0.5, 0.25, Vector3::new(0.0, 1.0, 1.0));
+        assert_direction(&view, 0.625, 0.25, Vector3::new(0.5, 2_f64.sqrt() / 2.0, 0.5));
+    }
+
+    #[test]
+    fn fisheye_maps_distance_from_center_to_angle() {
+        let mut specs = camera_specs(60.0);
+        specs.projection = Projection::Fisheye { fov: 180.0 };
+        let view = View::new(&specs, 1.0, 0.0);
+        assert_direction(&view, 0.5, 0.5, Vector3::new(0.0, 0.0, 1.0));
+        // Equidistant: halfway to the edge of the circle is half of the 90° to its edge
+        assert_direction(&view, 0.75, 0.5, Vector3::new(1.0, 0.0, 1.0));
+        assert_direction(&view, 1.0, 0.5, Vector3::new(1.0, 0.0, 0.0));
+        assert_direction(&view, 0.5, 0.0, Vector3::new(0.0, 1.0, 0.0));
+        // Outside of the image circle
+        assert!(view.ray(0.0, 0.0, 0.0).is_none());
+
+        // The circle fills the shorter side
+        let view = View::new(&specs, 2.0, 0.0);
+        assert_direction(&view, 0.75, 0.5, Vector3::new(1.0, 0.0, 0.0));
+        assert!(view.ray(0.9, 0.5, 0.0).is_none());
+
+        specs.projection = Projection::Fisheye { fov: 360.0 };
+        let view = View::new(&specs, 1.0, 0.0);
+        assert_direction(&view, 0.75, 0.5, Vector3::new(1.0, 0.0, 0.0));
+        assert_direction(&view, 0.5, 0.75, Vector3::new(0.0, -1.0, 0.0));
+        assert_direction(&view, 0.5, 1.0, Vector3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn stereo_eyes_are_offset_and_converge() {
+        let mut specs = camera_specs(60.0);
+        specs.stereo = Some(Stereo { layout: StereoLayout::SideBySide, eye_separation: 0.2, convergence: 5.0 });
+        let left = View::new(&specs, 1.0, -0.1).ray(0.5, 0.5, 0.0).unwrap();
+        let right = View::new(&specs, 1.0, 0.1).ray(0.5, 0.5, 0.0).unwrap();
+        assert!((left.origin - Vector3::new(0.9, 2.0, 3.0)).magnitude() < TOLERANCE, "{:?}", left);
+        assert!((right.origin - Vector3::new(1.1, 2.0, 3.0)).magnitude() < TOLERANCE, "{:?}", right);
+        // Both meet the camera's own center ray at the convergence distance
+        let converge_point = Vector3::new(1.0, 2.0, 3.0 + 5.0);
+        assert!((point_at_z(&left, 8.0) - converge_point).magnitude() < 1e-9, "{:?}", left);
+        assert!((point_at_z(&right, 8.0) - converge_point).magnitude() < 1e-9, "{:?}", right);
+    }
+
+    /// Renders a white sphere which only the left eye sees (at the center of its view),
+    /// against a blue background
+    fn render_stereo(layout: StereoLayout, width: usize, height: usize) -> FloatCanvas {
+        let mut specs = camera_specs(10.0);
+        specs.background_color = Color::new(0.0, 0.0, 1.0);
+        // Practically parallel eyes, 2.0 apart
+        specs.stereo = Some(Stereo { layout, eye_separation: 2.0, convergence: 1e9 });
+        let lights = vec![Light::Ambient { intensity: 1.0, color: Color::new(1.0, 1.0, 1.0) }];
+        let sphere = Sphere { center: Vector3::new(0.0, 2.0, 13.0), radius: 0.5, material: Material::new_with_defaults() };
+        let scene = Arc::new(RwLock::new(Scene { specs, lights, objects: vec![Box::new(sphere)] }));
+        let mut canvas = FloatCanvas::new(width, height);
+        render_to_float_canvas(&scene, &mut canvas);
+        canvas
+    }
+
+    #[test]
+    fn stereo_layouts() {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let blue = Color::new(0.0, 0.0, 1.0);
+
+        let canvas = render_stereo(StereoLayout::SideBySide, 2, 1);
+        assert_eq!(canvas.get_value(0, 0), white);
+        assert_eq!(canvas.get_value(1, 0), blue);
+
+        let canvas = render_stereo(StereoLayout::TopBottom, 1, 2);
+        assert_eq!(canvas.get_value(0, 0), white);
+        assert_eq!(canvas.get_value(0, 1), blue);
+
+        // Red from the left eye, green and blue from the right
+        let canvas = render_stereo(StereoLayout::Anaglyph, 1, 1);
+        assert_eq!(canvas.get_value(0, 0), Color::new(1.0, 0.0, 1.0));
+    }
+}
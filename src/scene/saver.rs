@@ -0,0 +1,288 @@
+//! Writes a scene back out as yaml, in the format read by `loader`.
+//!
+//! Every value is written out explicitly (rather than relying on the loader's defaults),
+//! and numbers are written with enough precision that loading the result reproduces
+//! the scene exactly.
+
+use std::any::Any;
+use std::fmt::{self, Write};
+use std::fs;
+use crate::cgmath::{Quaternion, Vector3};
+use crate::base::color::{Color, OutputTransfer};
+use crate::base::tone_mapping::ToneMapping;
+use crate::scene::camera::{Camera, Projection, StereoLayout};
+use crate::scene::mesh::{Mesh, MeshSource};
+use crate::scene::moving::Moving;
+use crate::scene::scene::{Attenuation, Light, Plane, SamplePattern, Scene, Specs, Sphere};
+use crate::scene::scene_error::{SceneError, SceneErrorKind};
+use crate::scene::shape::Material;
+use crate::scene::texture::{PatternKind, Texture, TextureSpace};
+
+/// Writes the scene to a yaml file which can be read by `loader::load`.
+pub fn save(scene: &Scene, filepath: &str) -> Result<(), SceneError> {
+    let string = to_yaml(scene)?;
+    fs::write(filepath, string).map_err(|e| SceneError {
+        kind: SceneErrorKind::File(e.to_string()),
+        path: String::new(),
+        location: None,
+    })
+}
+
+/// Returns the scene as a yaml string, in the format read by `loader::load_from_str`.
+///
+/// Objects must be spheres, planes, or meshes which were loaded from a scene file
+/// (other shapes have no representation in the file format).
+pub fn to_yaml(scene: &Scene) -> Result<String, SceneError> {
+    let mut out = String::new();
+    out.push_str("---\n");
+    write_specs(&mut out, &scene.specs).expect("write to string");
+    if let Some(camera) = &scene.specs.camera {
+        write_camera(&mut out, camera).expect("write to string");
+    }
+
+    out.push_str(if scene.lights.is_empty() { "\nlights: []\n" } else { "\nlights:\n" });
+    for light in &scene.lights {
+        write_light(&mut out, light).expect("write to string");
+    }
+
+    out.push_str(if scene.objects.is_empty() { "\nobjects: []\n" } else { "\nobjects:\n" });
+    for (i, object) in scene.objects.iter().enumerate() {
+        // A moving object is written as the object it wraps, plus its velocity
+        let object = object.as_ref().as_any();
+        let (object, velocity): (&dyn Any, _) = match object.downcast_ref::<Moving>() {
+            Some(moving) => (moving.shape.as_ref().as_any(), Some(moving.velocity)),
+            None => (object, None)
+        };
+        let result = if let Some(sphere) = object.downcast_ref::<Sphere>() {
+            write_sphere(&mut out, sphere)
+        } else if let Some(plane) = object.downcast_ref::<Plane>() {
+            write_plane(&mut out, plane)
+        } else if let Some(mesh) = object.downcast_ref::<Mesh>() {
+            let Some(source) = &mesh.source else {
+                return Err(unsupported(i, "mesh was not loaded from a file, and cannot be saved"));
+            };
+            write_mesh(&mut out, mesh, source)
+        } else {
+            return Err(unsupported(i, "unsupported object type, expected one of: sphere, plane, mesh"));
+        };
+        result.expect("write to string");
+        if let Some(velocity) = velocity {
+            writeln!(out, "      velocity: {}", vec3(&velocity)).expect("write to string");
+        }
+    }
+    Ok(out)
+}
+
+fn unsupported(index: usize, message: &str) -> SceneError {
+    SceneError {
+        kind: SceneErrorKind::InvalidValue(message.to_string()),
+        path: format!("objects[{}]", index),
+        location: None,
+    }
+}
+
+fn write_specs(out: &mut String, specs: &Specs) -> fmt::Result {
+    let sample_pattern = match specs.sample_pattern {
+        SamplePattern::Grid => "grid",
+        SamplePattern::Jittered => "jittered",
+    };
+    let tone_mapping = match specs.tone_mapping {
+        ToneMapping::Clamp => "clamp",
+        ToneMapping::Reinhard => "reinhard",
+        ToneMapping::AcesFilmic => "aces",
+    };
+    let output_transfer = match specs.output_transfer {
+        OutputTransfer::Linear => "linear",
+        OutputTransfer::Srgb => "srgb",
+    };
+    writeln!(out, "specs:")?;
+    writeln!(out, "  viewport_width: {:?}", specs.viewport_width)?;
+    writeln!(out, "  viewport_height: {:?}", specs.viewport_height)?;
+    writeln!(out, "  canvas_width: {:?}", specs.canvas_width)?;
+    writeln!(out, "  canvas_height: {:?}", specs.canvas_height)?;
+    writeln!(out, "  viewport_distance: {:?}", specs.viewport_distance)?;
+    writeln!(out, "  pixel_ar: {:?}", specs.pixel_ar)?;
+    writeln!(out, "  camera_pos: {}", vec3(&specs.camera_pos))?;
+    writeln!(out, "  camera_orientation: {}", quat(&specs.camera_orientation))?;
+    writeln!(out, "  camera_velocity: {}", vec3(&specs.camera_velocity))?;
+    match specs.projection {
+        Projection::Perspective => writeln!(out, "  projection: perspective")?,
+        Projection::Orthographic { height } => writeln!(out, "  projection: {{ orthographic: {{ height: {:?} }} }}", height)?,
+        Projection::Equirectangular => writeln!(out, "  projection: equirectangular")?,
+        Projection::Fisheye { fov } => writeln!(out, "  projection: {{ fisheye: {{ fov: {:?} }} }}", fov)?,
+    }
+    if let Some(stereo) = &specs.stereo {
+        let layout = match stereo.layout {
+            StereoLayout::SideBySide => "side_by_side",
+            StereoLayout::TopBottom => "top_bottom",
+            StereoLayout::Anaglyph => "anaglyph",
+        };
+        writeln!(out, "  stereo:")?;
+        writeln!(out, "    layout: {}", layout)?;
+        writeln!(out, "    eye_separation: {:?}", stereo.eye_separation)?;
+        writeln!(out, "    convergence: {:?}", stereo.convergence)?;
+    }
+    writeln!(out, "  background_color: {}", color(&specs.background_color))?;
+    writeln!(out, "  samples_per_pixel: {}", specs.samples_per_pixel)?;
+    writeln!(out, "  sample_pattern: {}", sample_pattern)?;
+    writeln!(out, "  light_samples: {}", specs.light_samples)?;
+    writeln!(out, "  shutter_open: {:?}", specs.shutter_open)?;
+    writeln!(out, "  shutter_close: {:?}", specs.shutter_close)?;
+    writeln!(out, "  tone_mapping: {}", tone_mapping)?;
+    writeln!(out, "  exposure: {:?}", specs.exposure)?;
+    writeln!(out, "  output_transfer: {}", output_transfer)
+}
+
+fn write_camera(out: &mut String, camera: &Camera) -> fmt::Result {
+    writeln!(out, "\ncamera:")?;
+    writeln!(out, "  position: {}", vec3(&camera.position))?;
+    writeln!(out, "  look_at: {}", vec3(&camera.look_at))?;
+    writeln!(out, "  up: {}", vec3(&camera.up))?;
+    writeln!(out, "  fov: {:?}", camera.fov)?;
+    writeln!(out, "  aperture: {:?}", camera.aperture)?;
+    writeln!(out, "  focus_distance: {:?}", camera.focus_distance)
+}
+
+fn write_light(out: &mut String, light: &Light) -> fmt::Result {
+    match light {
+        Light::Ambient { intensity, color: c } => {
+            writeln!(out, "  - ambient:")?;
+            writeln!(out, "      intensity: {:?}", intensity)?;
+            writeln!(out, "      color: {}", color(c))
+        }
+        Light::Point { intensity, color: c, position, attenuation, range, velocity } => {
+            writeln!(out, "  - point:")?;
+            writeln!(out, "      intensity: {:?}", intensity)?;
+            writeln!(out, "      color: {}", color(c))?;
+            writeln!(out, "      position: {}", vec3(position))?;
+            write_attenuation(out, attenuation, range)?;
+            writeln!(out, "      velocity: {}", vec3(velocity))
+        }
+        Light::Directional { intensity, color: c, direction } => {
+            writeln!(out, "  - directional:")?;
+            writeln!(out, "      intensity: {:?}", intensity)?;
+            writeln!(out, "      color: {}", color(c))?;
+            writeln!(out, "      direction: {}", vec3(direction))
+        }
+        Light::Spot { intensity, color: c, position, direction, inner_angle, outer_angle, attenuation, range, velocity } => {
+            writeln!(out, "  - spot:")?;
+            writeln!(out, "      intensity: {:?}", intensity)?;
+            writeln!(out, "      color: {}", color(c))?;
+            writeln!(out, "      position: {}", vec3(position))?;
+            writeln!(out, "      direction: {}", vec3(direction))?;
+            writeln!(out, "      inner_angle: {:?}", inner_angle)?;
+            writeln!(out, "      outer_angle: {:?}", outer_angle)?;
+            write_attenuation(out, attenuation, range)?;
+            writeln!(out, "      velocity: {}", vec3(velocity))
+        }
+        Light::Rect { intensity, color: c, position, edge_u, edge_v, velocity } => {
+            writeln!(out, "  - rect:")?;
+            writeln!(out, "      intensity: {:?}", intensity)?;
+            writeln!(out, "      color: {}", color(c))?;
+            writeln!(out, "      position: {}", vec3(position))?;
+            writeln!(out, "      edge_u: {}", vec3(edge_u))?;
+            writeln!(out, "      edge_v: {}", vec3(edge_v))?;
+            writeln!(out, "      velocity: {}", vec3(velocity))
+        }
+        Light::Sphere { intensity, color: c, position, radius, velocity } => {
+            writeln!(out, "  - sphere:")?;
+            writeln!(out, "      intensity: {:?}", intensity)?;
+            writeln!(out, "      color: {}", color(c))?;
+            writeln!(out, "      position: {}", vec3(position))?;
+            writeln!(out, "      radius: {:?}", radius)?;
+            writeln!(out, "      velocity: {}", vec3(velocity))
+        }
+    }
+}
+
+fn write_attenuation(out: &mut String, attenuation: &Attenuation, range: &Option<f64>) -> fmt::Result {
+    match attenuation {
+        Attenuation::None => writeln!(out, "      attenuation: none")?,
+        Attenuation::InverseSquare => writeln!(out, "      attenuation: inverse_square")?,
+        Attenuation::Coefficients { constant, linear, quadratic } => writeln!(out,
+            "      attenuation: {{ constant: {:?}, linear: {:?}, quadratic: {:?} }}", constant, linear, quadratic)?
+    }
+    // No range is represented by its absence
+    if let Some(range) = range {
+        writeln!(out, "      range: {:?}", range)?;
+    }
+    Ok(())
+}
+
+fn write_sphere(out: &mut String, sphere: &Sphere) -> fmt::Result {
+    writeln!(out, "  - sphere:")?;
+    writeln!(out, "      center: {}", vec3(&sphere.center))?;
+    writeln!(out, "      radius: {:?}", sphere.radius)?;
+    write_material(out, &sphere.material)
+}
+
+fn write_plane(out: &mut String, plane: &Plane) -> fmt::Result {
+    writeln!(out, "  - plane:")?;
+    writeln!(out, "      point: {}", vec3(&plane.point))?;
+    writeln!(out, "      normal: {}", vec3(&plane.normal))?;
+    write_material(out, &plane.material)
+}
+
+fn write_mesh(out: &mut String, mesh: &Mesh, source: &MeshSource) -> fmt::Result {
+    writeln!(out, "  - mesh:")?;
+    writeln!(out, "      file: {}", string(&source.file))?;
+    writeln!(out, "      position: {}", vec3(&source.position))?;
+    writeln!(out, "      scale: {}", vec3(&source.scale))?;
+    writeln!(out, "      rotation: {}", vec3(&source.rotation))?;
+    write_material(out, &mesh.material)
+}
+
+fn write_material(out: &mut String, material: &Material) -> fmt::Result {
+    writeln!(out, "      color: {}", texture(&material.color))?;
+    writeln!(out, "      specular: {:?}", material.specular)?;
+    writeln!(out, "      reflective: {:?}", material.reflective)?;
+    writeln!(out, "      transparency: {:?}", material.transparency)?;
+    writeln!(out, "      ior: {:?}", material.ior)
+}
+
+// Numbers use `{:?}`, which always includes a decimal point or exponent (so the loader
+// reads them as reals), and is the shortest representation which parses back exactly.
+
+fn vec3(v: &Vector3<f64>) -> String {
+    format!("[{:?}, {:?}, {:?}]", v.x, v.y, v.z)
+}
+
+fn quat(q: &Quaternion<f64>) -> String {
+    format!("[{:?}, {:?}, {:?}, {:?}]", q.s, q.v.x, q.v.y, q.v.z)
+}
+
+/// Written as (linear) unit values rather than (sRGB) 0-255, so that no precision is lost
+fn color(c: &Color) -> String {
+    format!("[{:?}, {:?}, {:?}]", c.r, c.g, c.b)
+}
+
+/// Either a color, or a single-line hash with the pattern type as its key
+fn texture(texture: &Texture) -> String {
+    let pattern = match texture {
+        Texture::Solid(c) => return color(c),
+        Texture::Pattern(pattern) => pattern
+    };
+    let (name, turbulence) = match pattern.kind {
+        PatternKind::Checker => ("checker", None),
+        PatternKind::Stripes => ("stripes", None),
+        PatternKind::Gradient => ("gradient", None),
+        PatternKind::Noise => ("noise", None),
+        PatternKind::Marble { turbulence } => ("marble", Some(turbulence)),
+        PatternKind::Wood { turbulence } => ("wood", Some(turbulence)),
+    };
+    let space = match pattern.space {
+        TextureSpace::Object => "object",
+        TextureSpace::Uv => "uv",
+    };
+    let turbulence = match turbulence {
+        Some(turbulence) => format!(", turbulence: {:?}", turbulence),
+        None => String::new()
+    };
+    format!("{{ {}: {{ colors: [{}, {}], scale: {:?}, space: {}{} }} }}",
+            name, color(&pattern.colors[0]), color(&pattern.colors[1]), pattern.scale, space, turbulence)
+}
+
+/// Double-quoted, so that file names which look like other yaml values stay strings
+fn string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
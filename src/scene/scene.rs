@@ -1,19 +1,36 @@
-use crate::cgmath::{Quaternion, Vector3};
-use crate::base::color::Color;
+use crate::cgmath::{InnerSpace, Quaternion, Vector2, Vector3};
+use crate::base::color::{Color, OutputTransfer};
+use crate::base::tone_mapping::ToneMapping;
+use crate::scene::camera::{Camera, Projection, Stereo};
+use crate::scene::shape::{Aabb, Hit, Material, Ray, Shape};
+use crate::util::maths;
 
 /// Simple data structure of the objects for a 3d scene, including lights.
 /// Plus the `specs` needed to render the scene.
 /// This is the scene's data model.
 #[derive(Debug)]
 pub struct Scene {
-    pub spheres: Vec::<Sphere>,
+    pub objects: Vec::<Box<dyn Shape>>,
     pub lights: Vec::<Light>,
     pub specs: Specs
 }
 
+impl Scene {
+    /// Returns the object at the given index as its concrete type,
+    /// or `None` if it is of some other type.
+    pub fn get_object<T: Shape>(&self, index: usize) -> Option<&T> {
+        self.objects.get(index)?.as_ref().as_any().downcast_ref::<T>()
+    }
+
+    /// Mutable version of `get_object`, useful for animating objects.
+    pub fn get_object_mut<T: Shape>(&mut self, index: usize) -> Option<&mut T> {
+        self.objects.get_mut(index)?.as_mut().as_any_mut().downcast_mut::<T>()
+    }
+}
+
 // ---
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Specs {
     /// Canvas dimensions in world units (unclear about how this is useful tbh)
     pub canvas_width: f64,
@@ -35,8 +52,55 @@ pub struct Specs {
     pub camera_pos: Vector3<f64>,
     pub camera_orientation: Quaternion<f64>,
 
+    /// Look-at camera. When set, takes the place of the camera and viewport values above.
+    pub camera: Option<Camera>,
+
+    /// Applies to either form of camera. If orthographic, the viewport values and field of view are unused.
+    pub projection: Projection,
+
+    /// Applies to either form of camera. When set, renders a view for each eye.
+    pub stereo: Option<Stereo>,
+
+    /// Motion blur. Each sample is taken at a random time between when the shutter opens and closes,
+    /// in frames (from 0.0 to 1.0). Objects (see `Moving`), lights and the camera move by their
+    /// velocity per frame. When both are the same, there is no motion blur.
+    /// Values outside of 0.0 to 1.0 (or a `shutter_close` before `shutter_open`) get clamped when rendering.
+    pub shutter_open: f64,
+    pub shutter_close: f64,
+    /// Applies to either form of camera, in world units per frame
+    pub camera_velocity: Vector3<f64>,
+
     /// Background color
     pub background_color: Color,
+
+    /// Anti-aliasing. Number of rays cast per pixel, whose colors get averaged.
+    /// Any count is used as-is: the pixel is divided into that many cells, in rows of near-equal
+    /// length (so a square number makes a square grid), with one sample per cell.
+    pub samples_per_pixel: usize,
+    pub sample_pattern: SamplePattern,
+
+    /// Number of shadow rays per shading point for area lights, which determines
+    /// the smoothness of their soft shadows. Laid out like `samples_per_pixel`.
+    pub light_samples: usize,
+
+    /// How rendered values get mapped to the displayable range, when written to a display canvas.
+    /// `exposure` is in stops (ie, each +1.0 doubles the brightness), and is applied beforehand.
+    pub tone_mapping: ToneMapping,
+    pub exposure: f64,
+
+    /// Encoding applied after tone mapping, when writing to a display canvas.
+    /// Lighting is computed in linear space, so this would normally be `Srgb`.
+    pub output_transfer: OutputTransfer,
+}
+
+/// How a pixel's samples are positioned within the pixel
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SamplePattern {
+    /// Samples at the centers of a regular grid of sub-pixel cells
+    Grid,
+    /// Samples at random positions within each sub-pixel cell (stratified sampling),
+    /// trading aliasing artifacts for noise
+    Jittered,
 }
 
 impl Specs {
@@ -50,26 +114,190 @@ impl Specs {
             pixel_ar: 1.0,
             camera_pos: Vector3::<f64>::new(0.0, 0.0, 0.0),
             camera_orientation: Quaternion::<f64>::new(1.0, 0.0, 0.0, 0.0),
+            camera: None,
+            projection: Projection::Perspective,
+            stereo: None,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+            camera_velocity: Vector3::<f64>::new(0.0, 0.0, 0.0),
             background_color: Color::new_black(),
+            samples_per_pixel: 1,
+            sample_pattern: SamplePattern::Grid,
+            light_samples: 16,
+            tone_mapping: ToneMapping::Clamp,
+            exposure: 0.0,
+            output_transfer: OutputTransfer::Srgb,
         }
     }
 }
 
 // ---
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Sphere {
     pub center: Vector3<f64>,
     pub radius: f64,
-    pub color: Color,
-    pub specular: f64,
-    pub reflective: f64,
-    pub transparency: f64,
+    pub material: Material,
+}
+
+impl Shape for Sphere {
+    fn intersect(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit> {
+        let r = self.radius;
+        let c0 = ray.origin - self.center;
+        let a = ray.direction.magnitude2(); // ie, d dot d
+        let b = 2.0 * c0.dot(ray.direction);
+        let c = c0.dot(c0) - (r * r);
+
+        let discriminant = b * b  -  4.0 * a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        // The two 'distances' on the ray where it intersects the sphere; use the closer valid one
+        let t1 = (-b - discriminant.sqrt()) / (2.0 * a);
+        let t2 = (-b + discriminant.sqrt()) / (2.0 * a);
+        let t = if maths::contains(t1, t_min, t_max) {
+            t1
+        } else if maths::contains(t2, t_min, t_max) {
+            t2
+        } else {
+            return None;
+        };
+        let local = ray.at(t) - self.center;
+        let normal = local / r;
+        // Longitude (with 0.5 facing -z) and latitude (0.0 at the bottom)
+        let u = 0.5 + normal.x.atan2(-normal.z) / std::f64::consts::TAU;
+        let v = 0.5 + normal.y.clamp(-1.0, 1.0).asin() / std::f64::consts::PI;
+        Some(Hit { t, normal, local, uv: Vector2::new(u, v) })
+    }
+
+    fn bounds(&self) -> Aabb {
+        let extent = Vector3::new(self.radius, self.radius, self.radius);
+        Aabb::new(self.center - extent, self.center + extent)
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
 }
 
-#[derive(Clone, Copy, Debug)]
+/// Infinite plane, defined by a point lying on the plane and the plane's normal.
+/// The normal need not be normalized.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plane {
+    pub point: Vector3<f64>,
+    pub normal: Vector3<f64>,
+    pub material: Material,
+}
+
+impl Shape for Plane {
+    fn intersect(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit> {
+        // A ray running parallel to the plane never intersects it
+        let denominator = self.normal.dot(ray.direction);
+        if denominator.abs() < f64::EPSILON {
+            return None;
+        }
+        let t = (self.point - ray.origin).dot(self.normal) / denominator;
+        if !maths::contains(t, t_min, t_max) {
+            return None;
+        }
+        // The plane's own space has the normal as its y axis, so that it lies exactly on y = 0
+        let normal = self.normal.normalize();
+        let (tangent, bitangent) = plane_axes(normal);
+        let offset = ray.at(t) - self.point;
+        let (u, v) = (offset.dot(tangent), offset.dot(bitangent));
+        Some(Hit { t, normal, local: Vector3::new(u, 0.0, v), uv: Vector2::new(u, v) })
+    }
+
+    fn bounds(&self) -> Aabb {
+        Aabb::infinite()
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+}
+
+/// Returns the x and z axes of a plane's own space, given its normal (its y axis).
+/// For a horizontal plane, these are the world's x and z axes.
+fn plane_axes(normal: Vector3<f64>) -> (Vector3<f64>, Vector3<f64>) {
+    let mut tangent = normal.cross(Vector3::unit_z());
+    if tangent.magnitude2() < f64::EPSILON {
+        tangent = normal.cross(Vector3::unit_x());
+    }
+    let tangent = tangent.normalize();
+    (tangent, tangent.cross(normal))
+}
+
+/// Each light's `color` gets scaled by its `intensity`.
+/// Lights which have a position can also have a `velocity` (in world units per frame; see `Specs::shutter_open`).
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Light {
-    Ambient { intensity: f64 },
-    Point { intensity: f64, position: Vector3<f64> },
-    Directional { intensity: f64, direction: Vector3<f64> }
+    Ambient { intensity: f64, color: Color },
+    /// Beyond `range` (if any), the light has no effect
+    Point {
+        intensity: f64,
+        color: Color,
+        position: Vector3<f64>,
+        attenuation: Attenuation,
+        range: Option<f64>,
+        velocity: Vector3<f64>,
+    },
+    Directional { intensity: f64, color: Color, direction: Vector3<f64> },
+    /// Point light restricted to a cone around `direction`.
+    /// Angles are in degrees, measured from the cone's axis. Full intensity within `inner_angle`,
+    /// falling off smoothly to nothing at `outer_angle`.
+    Spot {
+        intensity: f64,
+        color: Color,
+        position: Vector3<f64>,
+        direction: Vector3<f64>,
+        inner_angle: f64,
+        outer_angle: f64,
+        attenuation: Attenuation,
+        range: Option<f64>,
+        velocity: Vector3<f64>,
+    },
+    /// Rectangular area light, centered on `position`, with sides `edge_u` and `edge_v`.
+    /// Emits from both faces. Casts soft shadows, using `Specs::light_samples` shadow rays.
+    Rect {
+        intensity: f64,
+        color: Color,
+        position: Vector3<f64>,
+        edge_u: Vector3<f64>,
+        edge_v: Vector3<f64>,
+        velocity: Vector3<f64>,
+    },
+    /// Spherical area light. Casts soft shadows, using `Specs::light_samples` shadow rays.
+    Sphere {
+        intensity: f64,
+        color: Color,
+        position: Vector3<f64>,
+        radius: f64,
+        velocity: Vector3<f64>,
+    }
+}
+
+/// How a light's intensity falls off with distance
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Attenuation {
+    /// Same intensity at any distance
+    None,
+    /// Physically-based falloff, `1 / d²`
+    InverseSquare,
+    /// `1 / (constant + linear * d + quadratic * d²)`, for finer artistic control
+    Coefficients { constant: f64, linear: f64, quadratic: f64 },
+}
+
+impl Attenuation {
+    /// Returns the factor to scale the light's intensity by, at the given distance
+    pub fn factor(&self, distance: f64) -> f64 {
+        let denominator = match self {
+            Attenuation::None => return 1.0,
+            Attenuation::InverseSquare => distance * distance,
+            Attenuation::Coefficients { constant, linear, quadratic } =>
+                constant + linear * distance + quadratic * distance * distance
+        };
+        if denominator > 0.0 { 1.0 / denominator } else { 1.0 }
+    }
 }
@@ -0,0 +1,49 @@
+use std::error::Error;
+use std::fmt;
+
+/// Describes why a scene file could not be loaded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SceneError {
+    pub kind: SceneErrorKind,
+    /// Path to the offending field within the document (eg, `objects[3].sphere.radius`).
+    /// Empty when the error applies to the file as a whole.
+    pub path: String,
+    /// Position in the file (1-based line and column), when known.
+    /// For a missing field, this is the position of its parent.
+    pub location: Option<(usize, usize)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SceneErrorKind {
+    /// The file could not be read
+    File(String),
+    /// The file is not valid yaml
+    Syntax(String),
+    /// A required field is absent
+    Missing { expected: String },
+    /// A field holds a value of the wrong type
+    WrongType { expected: String, found: String },
+    /// A field holds a value of the right type, but it is otherwise unusable
+    InvalidValue(String),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        match &self.kind {
+            SceneErrorKind::File(message) => write!(f, "could not read file ({})", message)?,
+            SceneErrorKind::Syntax(message) => write!(f, "syntax error ({})", message)?,
+            SceneErrorKind::Missing { expected } => write!(f, "missing, expected {}", expected)?,
+            SceneErrorKind::WrongType { expected, found } => write!(f, "expected {}, found {}", expected, found)?,
+            SceneErrorKind::InvalidValue(message) => write!(f, "{}", message)?,
+        }
+        if let Some((line, column)) = self.location {
+            write!(f, " (line {}, column {})", line, column)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for SceneError {}
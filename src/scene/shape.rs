@@ -0,0 +1,149 @@
+use std::any::Any;
+use std::fmt::Debug;
+use crate::cgmath::{Vector2, Vector3};
+use crate::base::color::Color;
+use crate::scene::texture::Texture;
+
+/// Interface for any geometry which can be placed in a `Scene`.
+///
+/// Implement this to add custom primitives. The renderer only ever interacts with objects
+/// through this trait, so a custom shape gets lighting, shadows, reflection and transparency
+/// for free.
+pub trait Shape: AsAny + Debug + Send + Sync {
+    /// Returns the closest intersection of the ray with the shape,
+    /// whose distance along the ray lies within `t_min` and `t_max` (inclusive).
+    fn intersect(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit>;
+
+    /// Axis-aligned box fully containing the shape.
+    /// Unbounded shapes (eg, planes) should return `Aabb::infinite()`.
+    fn bounds(&self) -> Aabb;
+
+    fn material(&self) -> &Material;
+}
+
+/// Gives access to a shape's concrete type, via `Any`, for downcasting.
+/// Implemented for all types, so shapes needn't implement it themselves.
+pub trait AsAny: Any {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Half-line starting at `origin`, going in `direction`.
+/// `direction` is not necessarily normalized, so distances along the ray are in units of its length.
+#[derive(Clone, Copy, Debug)]
+pub struct Ray {
+    pub origin: Vector3<f64>,
+    pub direction: Vector3<f64>,
+    /// Moment at which the ray is cast, in frames (see `Specs::shutter_open`).
+    /// Moving objects get intersected at where they are at this time.
+    pub time: f64,
+}
+
+impl Ray {
+    pub fn new(origin: Vector3<f64>, direction: Vector3<f64>) -> Ray {
+        Ray { origin, direction, time: 0.0 }
+    }
+
+    pub fn new_at_time(origin: Vector3<f64>, direction: Vector3<f64>, time: f64) -> Ray {
+        Ray { origin, direction, time }
+    }
+
+    /// Point on the ray at the given distance
+    pub fn at(&self, t: f64) -> Vector3<f64> {
+        self.origin + self.direction * t
+    }
+}
+
+/// Describes where a ray intersects a shape.
+#[derive(Clone, Copy, Debug)]
+pub struct Hit {
+    /// Distance along the ray
+    pub t: f64,
+    /// Normalized surface normal at the intersection point.
+    /// Points to the 'outside' of the shape, regardless of which side the ray came from.
+    pub normal: Vector3<f64>,
+    /// Intersection point in the shape's own space (eg, relative to a sphere's center), for textures
+    pub local: Vector3<f64>,
+    /// Surface coordinates at the intersection point, for textures.
+    /// In the range 0.0 to 1.0, except for unbounded shapes (eg, planes), whose are in world units.
+    pub uv: Vector2<f64>,
+}
+
+/// Surface properties of a shape.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Material {
+    pub color: Texture,
+    pub specular: f64,
+    pub reflective: f64,
+    pub transparency: f64,
+    /// Index of refraction, used by transparent objects (eg, 1.0 for air, ~1.5 for glass)
+    pub ior: f64,
+}
+
+impl Material {
+    /// White, matte, opaque
+    pub fn new_with_defaults() -> Material {
+        Material {
+            color: Texture::Solid(Color::new(1.0, 1.0, 1.0)),
+            specular: -1.0,
+            reflective: 0.0,
+            transparency: 0.0,
+            ior: 1.0,
+        }
+    }
+}
+
+/// Axis-aligned bounding box
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb {
+    pub min: Vector3<f64>,
+    pub max: Vector3<f64>,
+}
+
+impl Aabb {
+    pub fn new(min: Vector3<f64>, max: Vector3<f64>) -> Aabb {
+        Aabb { min, max }
+    }
+
+    pub fn infinite() -> Aabb {
+        let min = Vector3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+        let max = Vector3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        Aabb { min, max }
+    }
+
+    /// Smallest box containing all the given points
+    pub fn from_points(points: &[Vector3<f64>]) -> Aabb {
+        let min = Vector3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let max = Vector3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+        let mut result = Aabb { min, max };
+        for point in points {
+            result = result.union(&Aabb::new(*point, *point));
+        }
+        result
+    }
+
+    /// Smallest box containing both boxes
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: Vector3::new(
+                self.min.x.min(other.min.x), self.min.y.min(other.min.y), self.min.z.min(other.min.z)),
+            max: Vector3::new(
+                self.max.x.max(other.max.x), self.max.y.max(other.max.y), self.max.z.max(other.max.z)),
+        }
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.min.x.is_finite() && self.min.y.is_finite() && self.min.z.is_finite()
+            && self.max.x.is_finite() && self.max.y.is_finite() && self.max.z.is_finite()
+    }
+}
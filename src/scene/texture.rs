@@ -0,0 +1,176 @@
+use std::f64::consts::TAU;
+use crate::cgmath::Vector3;
+use crate::base::color::Color;
+use crate::scene::shape::Hit;
+use crate::util::noise;
+
+/// Layers of noise used by the turbulent patterns
+const TURBULENCE_OCTAVES: usize = 6;
+
+/// Color of a surface, which can vary across it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Texture {
+    Solid(Color),
+    Pattern(Pattern),
+}
+
+impl Texture {
+    /// Returns the surface's color at the intersection
+    pub fn color_at(&self, hit: &Hit) -> Color {
+        match self {
+            Texture::Solid(color) => *color,
+            Texture::Pattern(pattern) => pattern.color_at(hit)
+        }
+    }
+}
+
+impl From<Color> for Texture {
+    fn from(color: Color) -> Texture {
+        Texture::Solid(color)
+    }
+}
+
+/// Procedural pattern, alternating or blending between two colors
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub colors: [Color; 2],
+    /// Size of one repeat of the pattern (eg, of a checker square), in the units of `space`
+    pub scale: f64,
+    pub space: TextureSpace,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PatternKind {
+    /// Alternates in a grid of cubes (or of squares, in uv space)
+    Checker,
+    /// Alternates in bands along x (or u)
+    Stripes,
+    /// Blends from the first color to the second along x (or u), and repeats
+    Gradient,
+    /// Smooth, random-looking blend of the two colors
+    Noise,
+    /// Veins running across x (or u), distorted by `turbulence`
+    Marble { turbulence: f64 },
+    /// Rings around the y axis, distorted by `turbulence`
+    Wood { turbulence: f64 },
+}
+
+/// The coordinates a pattern is evaluated in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextureSpace {
+    /// The shape's own 3d space, in world units (see `Hit::local`).
+    /// The pattern appears carved out of a solid block.
+    Object,
+    /// The shape's 2d surface coordinates (see `Hit::uv`). The pattern appears wrapped around the shape.
+    Uv,
+}
+
+impl Pattern {
+    pub fn color_at(&self, hit: &Hit) -> Color {
+        let p = match self.space {
+            TextureSpace::Object => hit.local,
+            TextureSpace::Uv => Vector3::new(hit.uv.x, hit.uv.y, 0.0)
+        } / self.scale;
+
+        // How far from the first color to the second
+        let value = match self.kind {
+            PatternKind::Checker => (p.x.floor() + p.y.floor() + p.z.floor()).rem_euclid(2.0),
+            PatternKind::Stripes => p.x.floor().rem_euclid(2.0),
+            PatternKind::Gradient => p.x - p.x.floor(),
+            PatternKind::Noise => (noise::perlin(p) + 1.0) * 0.5,
+            PatternKind::Marble { turbulence } => {
+                let x = p.x + turbulence * noise::turbulence(p, TURBULENCE_OCTAVES);
+                0.5 + 0.5 * (x * TAU).sin()
+            },
+            PatternKind::Wood { turbulence } => {
+                let r = (p.x * p.x + p.z * p.z).sqrt() + turbulence * noise::turbulence(p, TURBULENCE_OCTAVES);
+                r - r.floor()
+            }
+        };
+        Color::lerp(self.colors[0], self.colors[1], value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cgmath::Vector2;
+
+    const BLACK: Color = Color { r: 0.0, g: 0.0, b: 0.0 };
+    const WHITE: Color = Color { r: 1.0, g: 1.0, b: 1.0 };
+
+    fn pattern(kind: PatternKind, scale: f64, space: TextureSpace) -> Pattern {
+        Pattern { kind, colors: [BLACK, WHITE], scale, space }
+    }
+
+    fn hit_at(x: f64, y: f64, z: f64) -> Hit {
+        Hit { t: 1.0, normal: Vector3::new(0.0, 1.0, 0.0), local: Vector3::new(x, y, z), uv: Vector2::new(x, y) }
+    }
+
+    #[test]
+    fn checker_alternates_across_cell_boundaries() {
+        let checker = pattern(PatternKind::Checker, 1.0, TextureSpace::Object);
+        assert_eq!(checker.color_at(&hit_at(0.5, 0.5, 0.5)), BLACK);
+        // Across any one boundary...
+        assert_eq!(checker.color_at(&hit_at(1.01, 0.5, 0.5)), WHITE);
+        assert_eq!(checker.color_at(&hit_at(0.5, 1.01, 0.5)), WHITE);
+        assert_eq!(checker.color_at(&hit_at(0.5, 0.5, 1.01)), WHITE);
+        assert_eq!(checker.color_at(&hit_at(-0.01, 0.5, 0.5)), WHITE);
+        // ...but not two
+        assert_eq!(checker.color_at(&hit_at(1.01, 1.01, 0.5)), BLACK);
+        assert_eq!(checker.color_at(&hit_at(-0.01, -0.01, 0.5)), BLACK);
+    }
+
+    #[test]
+    fn checker_cells_are_scale_wide() {
+        let checker = pattern(PatternKind::Checker, 0.5, TextureSpace::Object);
+        assert_eq!(checker.color_at(&hit_at(0.49, 0.1, 0.1)), BLACK);
+        assert_eq!(checker.color_at(&hit_at(0.51, 0.1, 0.1)), WHITE);
+        assert_eq!(checker.color_at(&hit_at(1.01, 0.1, 0.1)), BLACK);
+    }
+
+    #[test]
+    fn checker_in_uv_space_ignores_local_point() {
+        let checker = pattern(PatternKind::Checker, 0.25, TextureSpace::Uv);
+        let mut hit = hit_at(0.1, 0.1, 5.5);
+        assert_eq!(checker.color_at(&hit), BLACK);
+        hit.uv = Vector2::new(0.3, 0.1);
+        assert_eq!(checker.color_at(&hit), WHITE);
+    }
+
+    #[test]
+    fn stripes_repeat_every_two_scales_along_x() {
+        let stripes = pattern(PatternKind::Stripes, 0.5, TextureSpace::Object);
+        for x in [-0.9, -0.3, 0.1, 0.4, 0.7, 1.2] {
+            let color = stripes.color_at(&hit_at(x, 0.0, 0.0));
+            assert_eq!(stripes.color_at(&hit_at(x + 1.0, 0.0, 0.0)), color, "{}", x);
+            assert_ne!(stripes.color_at(&hit_at(x + 0.5, 0.0, 0.0)), color, "{}", x);
+            // Bands are unaffected by y and z
+            assert_eq!(stripes.color_at(&hit_at(x, 3.7, -2.2)), color, "{}", x);
+        }
+    }
+
+    #[test]
+    fn gradient_blends_within_each_repeat() {
+        let gradient = pattern(PatternKind::Gradient, 2.0, TextureSpace::Object);
+        assert_eq!(gradient.color_at(&hit_at(0.0, 0.0, 0.0)), BLACK);
+        assert_eq!(gradient.color_at(&hit_at(0.5, 0.0, 0.0)), Color::new(0.25, 0.25, 0.25));
+        assert_eq!(gradient.color_at(&hit_at(2.5, 0.0, 0.0)), Color::new(0.25, 0.25, 0.25));
+    }
+
+    #[test]
+    fn turbulent_patterns_stay_between_their_colors() {
+        let kinds = [PatternKind::Noise, PatternKind::Marble { turbulence: 2.0 }, PatternKind::Wood { turbulence: 2.0 }];
+        for kind in kinds {
+            let pattern = pattern(kind, 0.7, TextureSpace::Object);
+            for i in 0..200 {
+                let t = i as f64 * 0.173;
+                let color = pattern.color_at(&hit_at(t.sin() * 3.0, t * 0.5, t.cos() * 2.0));
+                assert!((0.0..=1.0).contains(&color.r), "{:?}: {:?}", kind, color);
+                // The same point always gets the same color
+                assert_eq!(pattern.color_at(&hit_at(t.sin() * 3.0, t * 0.5, t.cos() * 2.0)), color);
+            }
+        }
+    }
+}
@@ -1,7 +1,7 @@
-/// ANSI escape sequences
-///
-/// Library uses these convenience functions and values
-/// for doing any terminal ANSI manipulations.
+//! ANSI escape sequences
+//!
+//! Library uses these convenience functions and values
+//! for doing any terminal ANSI manipulations.
 
 pub static PART_START: &str = "\x1b["; // rem, "\x1b" is a single character
 pub static PART_END: &str = "m";
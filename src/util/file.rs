@@ -1,15 +1,15 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Recursively goes up the file tree starting at `dir_path`,
 /// looking for file named `target_file_name`.
-pub fn find_file_starting_from(dir_path: &PathBuf, target_file_name: &str) -> Option<PathBuf> {
+pub fn find_file_starting_from(dir_path: &Path, target_file_name: &str) -> Option<PathBuf> {
 
     if !dir_path.is_dir() {
         return None;
     }
 
     // Add filename to the directory path. Does it exist?
-    let mut test_path = dir_path.clone();
+    let mut test_path = dir_path.to_path_buf();
     test_path.push(target_file_name);
 
     if test_path.exists() {
@@ -17,11 +17,11 @@ pub fn find_file_starting_from(dir_path: &PathBuf, target_file_name: &str) -> Op
     }
 
     // Go up one directory and try again
-    let mut dir_path = dir_path.clone();
+    let mut dir_path = dir_path.to_path_buf();
     if !dir_path.pop() {
         return None;
     }
-    return find_file_starting_from(&dir_path, &target_file_name);
+    find_file_starting_from(&dir_path, target_file_name)
 }
 
 /// Useful for finding file in rust project root dir during development (since cwd may or may not
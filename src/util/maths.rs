@@ -5,9 +5,9 @@ pub fn map(value: f64, value_min: f64, value_max: f64, dest_min: f64, dest_max:
 
 pub fn clamp<T: PartialOrd>(value: T, min: T, max: T) -> T {
     if value < min {
-        return min;
+        min
     } else if value > max {
-        return max;
+        max
     } else {
         value
     }
@@ -23,6 +23,15 @@ pub fn u8_add_clamped(a: u8, b: u8) -> u8 {
 }
 
 
+/// Hermite interpolation from 0.0 to 1.0, as `value` goes from `edge0` to `edge1`
+pub fn smoothstep(edge0: f64, edge1: f64, value: f64) -> f64 {
+    if edge0 == edge1 {
+        return if value < edge0 { 0.0 } else { 1.0 };
+    }
+    let t = clamp((value - edge0) / (edge1 - edge0), 0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
 /// Is value within the given range (inclusive)
 pub fn contains(value: f64, min: f64, max: f64) -> bool {
     value >= min && value <= max
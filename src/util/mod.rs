@@ -1,3 +1,5 @@
 pub mod ansi;
 pub mod maths;
+pub mod noise;
 pub mod file;
+pub mod random;
@@ -0,0 +1,122 @@
+//! Gradient noise (ie, Ken Perlin's "improved noise"), for procedural textures.
+//! Deterministic, so the same point always gives the same value.
+
+use crate::cgmath::Vector3;
+
+/// Smoothly varying noise, in the range of about -1.0 to 1.0, with features about 1.0 apart
+pub fn perlin(p: Vector3<f64>) -> f64 {
+    let (x0, y0, z0) = (p.x.floor(), p.y.floor(), p.z.floor());
+    let (x, y, z) = (p.x - x0, p.y - y0, p.z - z0);
+    let (cell_x, cell_y, cell_z) = (x0 as i64, y0 as i64, z0 as i64);
+
+    // Contribution of each corner of the lattice cell containing the point
+    let corner = |dx: i64, dy: i64, dz: i64| {
+        let hash = hash(cell_x + dx, cell_y + dy, cell_z + dz);
+        gradient(hash, x - dx as f64, y - dy as f64, z - dz as f64)
+    };
+
+    let (u, v, w) = (fade(x), fade(y), fade(z));
+    let y0_z0 = lerp(corner(0, 0, 0), corner(1, 0, 0), u);
+    let y1_z0 = lerp(corner(0, 1, 0), corner(1, 1, 0), u);
+    let y0_z1 = lerp(corner(0, 0, 1), corner(1, 0, 1), u);
+    let y1_z1 = lerp(corner(0, 1, 1), corner(1, 1, 1), u);
+    lerp(lerp(y0_z0, y1_z0, v), lerp(y0_z1, y1_z1, v), w)
+}
+
+/// Sum of `octaves` layers of noise, each at double the frequency and half the amplitude
+/// of the last, in the range 0.0 to about 1.0. Gives a turbulent look.
+pub fn turbulence(p: Vector3<f64>, octaves: usize) -> f64 {
+    let mut sum = 0.0;
+    let mut total_amplitude = 0.0;
+    let mut amplitude = 1.0;
+    let mut p = p;
+    for _ in 0..octaves {
+        sum += perlin(p).abs() * amplitude;
+        total_amplitude += amplitude;
+        amplitude *= 0.5;
+        p *= 2.0;
+    }
+    if total_amplitude > 0.0 { sum / total_amplitude } else { 0.0 }
+}
+
+/// Ease curve, `6t⁵ - 15t⁴ + 10t³`, so that the noise is smooth across cell boundaries
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+/// Pseudo-random value for a lattice point, in place of Perlin's permutation table
+fn hash(x: i64, y: i64, z: i64) -> u64 {
+    let mut h = (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+        ^ (z as u64).wrapping_mul(0x1656_67B1_9E37_79F9);
+    h ^= h >> 32;
+    h = h.wrapping_mul(0xD6E8_FEB8_6659_FD93);
+    h ^ (h >> 32)
+}
+
+/// Dot product of the offset with one of 12 gradient directions (towards the edges of a cube),
+/// chosen by the hash
+fn gradient(hash: u64, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 { y } else if h == 12 || h == 14 { x } else { z };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Points spread irregularly over several lattice cells, including negative coordinates
+    fn sample_points() -> impl Iterator<Item = Vector3<f64>> {
+        (0..2000).map(|i| {
+            let t = i as f64 * 0.0137;
+            Vector3::new((t * 7.1).sin() * 9.0, t - 10.0, (t * 3.3).cos() * 5.5)
+        })
+    }
+
+    #[test]
+    fn perlin_is_deterministic() {
+        for p in sample_points() {
+            assert_eq!(perlin(p), perlin(p));
+        }
+        assert_eq!(perlin(Vector3::new(1.3, -2.7, 0.4)), perlin(Vector3::new(1.3, -2.7, 0.4)));
+    }
+
+    #[test]
+    fn perlin_is_in_range_and_varies() {
+        let values: Vec<f64> = sample_points().map(perlin).collect();
+        assert!(values.iter().all(|value| (-1.0..=1.0).contains(value)));
+        let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        assert!(min < -0.2 && max > 0.2, "{} to {}", min, max);
+    }
+
+    #[test]
+    fn perlin_is_zero_at_lattice_points() {
+        for (x, y, z) in [(0, 0, 0), (3, -2, 7), (-5, 1, -1)] {
+            assert_eq!(perlin(Vector3::new(x as f64, y as f64, z as f64)), 0.0);
+        }
+    }
+
+    #[test]
+    fn perlin_is_continuous() {
+        for p in sample_points() {
+            let step = Vector3::new(1e-6, 1e-6, 1e-6);
+            assert!((perlin(p + step) - perlin(p)).abs() < 1e-4, "{:?}", p);
+        }
+    }
+
+    #[test]
+    fn turbulence_is_in_range() {
+        for p in sample_points() {
+            let value = turbulence(p, 6);
+            assert!((0.0..=1.0).contains(&value), "{:?}: {}", p, value);
+        }
+        assert_eq!(turbulence(Vector3::new(0.5, 0.5, 0.5), 0), 0.0);
+    }
+}
@@ -0,0 +1,37 @@
+//! Minimal pseudo-random number generation (xorshift64*), for stochastic sampling.
+//! Not suitable for anything requiring quality randomness.
+
+use std::cell::Cell;
+
+thread_local! {
+    static STATE: Cell<u64> = Cell::new(seed());
+}
+
+/// Returns a value in the range [0, 1)
+pub fn next_f64() -> f64 {
+    STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        state.set(x);
+        let value = x.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        (value >> 11) as f64 / (1_u64 << 53) as f64
+    })
+}
+
+/// Returns a value in the range [min, max)
+pub fn range(min: f64, max: f64) -> f64 {
+    min + (max - min) * next_f64()
+}
+
+/// Makes a distinct, non-zero seed per thread
+fn seed() -> u64 {
+    let local = 0_u8;
+    let address = &local as *const u8 as u64;
+    let time = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or(0);
+    (address ^ time.rotate_left(32)) | 1
+}
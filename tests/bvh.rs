@@ -0,0 +1,99 @@
+use min_rt::cgmath::{InnerSpace, Vector3};
+use min_rt::scene::bvh::Bvh;
+use min_rt::scene::scene::{Plane, Sphere};
+use min_rt::scene::shape::{Hit, Material, Ray, Shape};
+
+/// Deterministic pseudo-random values (64-bit LCG), so failures are reproducible
+struct Random(u64);
+
+impl Random {
+    fn range(&mut self, min: f64, max: f64) -> f64 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        min + (max - min) * ((self.0 >> 11) as f64 / (1_u64 << 53) as f64)
+    }
+
+    fn vec3(&mut self, min: f64, max: f64) -> Vector3<f64> {
+        Vector3::new(self.range(min, max), self.range(min, max), self.range(min, max))
+    }
+}
+
+/// Many small spheres (enough for the tree to get split several levels deep), plus an unbounded plane
+fn make_shapes(random: &mut Random) -> Vec<Box<dyn Shape>> {
+    let mut shapes = Vec::<Box<dyn Shape>>::new();
+    for _ in 0..300 {
+        let center = random.vec3(-20.0, 20.0);
+        let radius = random.range(0.3, 2.0);
+        shapes.push(Box::new(Sphere { center, radius, material: Material::new_with_defaults() }));
+    }
+    // In the middle of the list, so it isn't simply appended at the end of the tree's items
+    shapes.insert(150, Box::new(Plane {
+        point: Vector3::new(0.0, -15.0, 0.0),
+        normal: Vector3::new(0.1, 1.0, 0.0),
+        material: Material::new_with_defaults(),
+    }));
+    shapes
+}
+
+fn make_ray(random: &mut Random) -> Ray {
+    let origin = random.vec3(-30.0, 30.0);
+    let direction = random.vec3(-1.0, 1.0).normalize();
+    Ray::new(origin, direction)
+}
+
+/// Closest hit found by testing every shape
+fn brute_force_closest(shapes: &[Box<dyn Shape>], ray: &Ray, t_min: f64, t_max: f64) -> Option<(Hit, usize)> {
+    let mut result: Option<(Hit, usize)> = None;
+    for (i, shape) in shapes.iter().enumerate() {
+        if let Some(hit) = shape.intersect(ray, t_min, t_max) {
+            match result {
+                Some((closest, _)) if closest.t <= hit.t => {},
+                _ => result = Some((hit, i))
+            }
+        }
+    }
+    result
+}
+
+#[test]
+fn closest_hit_matches_brute_force() {
+    let mut random = Random(1);
+    let shapes = make_shapes(&mut random);
+    let bvh = Bvh::from_shapes(&shapes);
+    let mut num_hits = 0;
+    for _ in 0..5000 {
+        let ray = make_ray(&mut random);
+        let expected = brute_force_closest(&shapes, &ray, 0.001, f64::INFINITY);
+        let actual = bvh.closest_hit(&ray, 0.001, f64::INFINITY, |i, t_max| shapes[i].intersect(&ray, 0.001, t_max));
+        match (expected, actual) {
+            (None, None) => {},
+            (Some((expected_hit, expected_index)), Some((actual_hit, actual_index))) => {
+                assert_eq!(expected_index, actual_index, "{:?}", ray);
+                assert_eq!(expected_hit.t, actual_hit.t, "{:?}", ray);
+                num_hits += 1;
+            },
+            _ => panic!("expected {:?}, got {:?}, for {:?}", expected, actual, ray)
+        }
+    }
+    // Sanity check that the test exercises both outcomes
+    assert!(num_hits > 500 && num_hits < 4500, "{} hits", num_hits);
+}
+
+#[test]
+fn any_hit_matches_brute_force() {
+    let mut random = Random(2);
+    let shapes = make_shapes(&mut random);
+    let bvh = Bvh::from_shapes(&shapes);
+    for _ in 0..5000 {
+        let ray = make_ray(&mut random);
+        // Limited distance, as for shadow rays
+        let t_max = random.range(1.0, 40.0);
+        let expected = brute_force_closest(&shapes, &ray, 0.001, t_max);
+        let actual = bvh.any_hit(&ray, 0.001, t_max, |i, t_max| shapes[i].intersect(&ray, 0.001, t_max));
+        assert_eq!(expected.is_some(), actual.is_some(), "{:?}, t_max {}", ray, t_max);
+        if let Some((hit, index)) = actual {
+            // Needn't be the closest, but must be a real hit within range
+            let check = shapes[index].intersect(&ray, 0.001, t_max).expect("hit");
+            assert_eq!(check.t, hit.t);
+        }
+    }
+}
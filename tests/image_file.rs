@@ -0,0 +1,85 @@
+use std::io::Cursor;
+use min_rt::base::color::Color;
+use min_rt::canvas::canvas::Canvas;
+use min_rt::canvas::float_canvas::FloatCanvas;
+use min_rt::canvas::image_file;
+
+/// Returns the PNG's pixels as rgb bytes, using an independent decoder
+fn decode_png(bytes: &[u8]) -> (u32, u32, Vec<u8>) {
+    let decoder = png::Decoder::new(Cursor::new(bytes));
+    let mut reader = decoder.read_info().expect("png header");
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).expect("png image data");
+    assert_eq!(info.color_type, png::ColorType::Rgb);
+    assert_eq!(info.bit_depth, png::BitDepth::Eight);
+    pixels.truncate(info.buffer_size());
+    (info.width, info.height, pixels)
+}
+
+fn expected_rgb(canvas: &dyn Canvas<Color>) -> Vec<u8> {
+    let mut result = Vec::new();
+    for y in 0..canvas.get_height() {
+        for x in 0..canvas.get_width() {
+            let (r, g, b) = canvas.get_value(x, y).to_u8();
+            result.extend([r, g, b]);
+        }
+    }
+    result
+}
+
+fn assert_round_trip(canvas: &dyn Canvas<Color>) {
+    let (width, height, pixels) = decode_png(&image_file::encode_png(canvas));
+    assert_eq!(width as usize, canvas.get_width());
+    assert_eq!(height as usize, canvas.get_height());
+    assert_eq!(pixels, expected_rgb(canvas));
+}
+
+#[test]
+fn png_round_trip_single_pixel() {
+    let mut canvas = FloatCanvas::new(1, 1);
+    canvas.set_value(0, 0, &Color::new(0.25, 0.5, 1.0));
+    assert_round_trip(&canvas);
+}
+
+#[test]
+fn png_round_trip_flat_color() {
+    // Long runs, which compress to maximum-length matches
+    let mut canvas = FloatCanvas::new(200, 50);
+    for y in 0..50 {
+        for x in 0..200 {
+            canvas.set_value(x, y, &Color::new(0.2, 0.4, 0.6));
+        }
+    }
+    assert_round_trip(&canvas);
+}
+
+#[test]
+fn png_round_trip_varied_image() {
+    // Gradients mixed with pseudo-random noise, over more than the 32KB deflate window
+    let (width, height) = (160, 120);
+    let mut canvas = FloatCanvas::new(width, height);
+    let mut state = 1_u32;
+    for y in 0..height {
+        for x in 0..width {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            let noise = (state >> 16) as f64 / 65536.0;
+            let color = if (x / 16 + y / 16) % 2 == 0 {
+                Color::new(x as f64 / width as f64, y as f64 / height as f64, 0.5)
+            } else {
+                Color::new(noise, 1.0 - noise, noise * 0.5)
+            };
+            canvas.set_value(x, y, &color);
+        }
+    }
+    assert_round_trip(&canvas);
+}
+
+#[test]
+fn ppm_has_header_and_rgb_bytes() {
+    let mut canvas = FloatCanvas::new(2, 1);
+    canvas.set_value(0, 0, &Color::new(1.0, 0.0, 0.0));
+    canvas.set_value(1, 0, &Color::new(0.0, 0.0, 1.0));
+    let mut expected = b"P6\n2 1\n255\n".to_vec();
+    expected.extend(expected_rgb(&canvas));
+    assert_eq!(image_file::encode_ppm(&canvas), expected);
+}
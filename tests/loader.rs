@@ -0,0 +1,268 @@
+use std::fs;
+use std::path::Path;
+use min_rt::base::color::Color;
+use min_rt::cgmath::{InnerSpace, Vector3};
+use min_rt::scene::loader;
+use min_rt::scene::mesh::Mesh;
+use min_rt::scene::scene::Specs;
+use min_rt::scene::scene_error::{SceneError, SceneErrorKind};
+use min_rt::scene::shape::Material;
+use min_rt::scene::texture::Texture;
+
+fn load_error(yaml: &str, strict: bool) -> SceneError {
+    match loader::load_from_str(yaml, Path::new(""), strict) {
+        Ok(_) => panic!("expected error"),
+        Err(e) => e
+    }
+}
+
+#[test]
+fn wrong_type_reports_path_and_location() {
+    let yaml = "\
+lights:
+  - ambient:
+      intensity: 0.5
+objects:
+  - sphere:
+      center: [0.0, 0.0, 3.0]
+      radius: big
+";
+    let e = load_error(yaml, true);
+    assert_eq!(e.path, "objects[0].sphere.radius");
+    assert_eq!(e.location, Some((7, 15)));
+    assert!(matches!(e.kind, SceneErrorKind::WrongType { .. }), "{:?}", e.kind);
+}
+
+#[test]
+fn missing_key_reports_path_and_parent_location() {
+    let yaml = "\
+lights:
+  - ambient:
+      intensity: 0.5
+objects:
+  - sphere:
+      center: [0.0, 0.0, 3.0]
+";
+    let e = load_error(yaml, true);
+    assert_eq!(e.path, "objects[0].sphere.radius");
+    assert_eq!(e.location, Some((5, 5)));
+    assert!(matches!(e.kind, SceneErrorKind::Missing { .. }), "{:?}", e.kind);
+}
+
+#[test]
+fn unknown_enum_string_reports_path_and_location() {
+    let yaml = "\
+specs:
+  tone_mapping: filmic
+lights: []
+";
+    let e = load_error(yaml, false);
+    assert_eq!(e.path, "specs.tone_mapping");
+    assert_eq!(e.location, Some((2, 17)));
+    assert_eq!(e.kind, SceneErrorKind::InvalidValue("expected one of: clamp, reinhard, aces".to_string()));
+}
+
+#[test]
+fn invalid_value_reports_path_and_location() {
+    let yaml = "\
+lights: []
+objects:
+  - sphere: { center: [0.0, 0.0, 3.0], radius: -1.0 }
+";
+    let e = load_error(yaml, true);
+    assert_eq!(e.path, "objects[0].sphere.radius");
+    assert_eq!(e.location, Some((3, 48)));
+    assert_eq!(e.to_string(), "objects[0].sphere.radius: expected radius greater than 0 (line 3, column 48)");
+}
+
+#[test]
+fn syntax_error_reports_location() {
+    let e = load_error("lights: [\n", false);
+    assert_eq!(e.path, "");
+    assert!(matches!(e.kind, SceneErrorKind::Syntax(_)), "{:?}", e.kind);
+    assert!(e.location.is_some());
+}
+
+const UNKNOWN_OBJECT_TYPE: &str = "\
+lights:
+  - ambient:
+      intensity: 0.5
+objects:
+  - sphere:
+      center: [0.0, 0.0, 3.0]
+      radius: 1.0
+  - cube:
+      size: 1.0
+";
+
+#[test]
+fn lenient_mode_skips_unknown_object_type() {
+    let scene = loader::load_from_str(UNKNOWN_OBJECT_TYPE, Path::new(""), false).unwrap();
+    assert_eq!(scene.objects.len(), 1);
+    assert_eq!(scene.lights.len(), 1);
+}
+
+#[test]
+fn strict_mode_rejects_unknown_object_type() {
+    let e = load_error(UNKNOWN_OBJECT_TYPE, true);
+    assert_eq!(e.path, "objects[1]");
+    assert_eq!(e.location, Some((8, 5)));
+    assert_eq!(e.kind, SceneErrorKind::InvalidValue("unknown object type, expected one of: sphere, plane, mesh".to_string()));
+}
+
+#[test]
+fn omitted_values_get_defaults() {
+    let yaml = "\
+lights:
+  - ambient:
+      intensity: 0.5
+objects:
+  - sphere:
+      center: [0.0, 0.0, 3.0]
+      radius: 1.0
+";
+    let scene = loader::load_from_str(yaml, Path::new(""), true).unwrap();
+    assert_eq!(scene.specs, Specs::new_with_defaults());
+    assert_eq!(*scene.objects[0].material(), Material::new_with_defaults());
+}
+
+#[test]
+fn omitted_material_values_get_file_defaults() {
+    let yaml = "\
+specs:
+  samples_per_pixel: 4
+lights: []
+defaults:
+  specular: 500.0
+  reflective: 0.2
+objects:
+  - sphere:
+      center: [0.0, 0.0, 3.0]
+      radius: 1.0
+  - sphere:
+      center: [0.0, 0.0, 3.0]
+      radius: 1.0
+      reflective: 0.5
+";
+    let scene = loader::load_from_str(yaml, Path::new(""), true).unwrap();
+    assert_eq!(scene.specs.samples_per_pixel, 4);
+    assert_eq!(scene.specs.light_samples, Specs::new_with_defaults().light_samples);
+
+    let expected = Material { specular: 500.0, reflective: 0.2, ..Material::new_with_defaults() };
+    assert_eq!(*scene.objects[0].material(), expected);
+    let expected = Material { reflective: 0.5, ..expected };
+    assert_eq!(*scene.objects[1].material(), expected);
+}
+
+fn load_sphere_color(color: &str) -> Result<Texture, SceneError> {
+    let yaml = format!("lights: []\nobjects:\n  - sphere: {{ center: [0.0, 0.0, 3.0], radius: 1.0, color: {} }}\n", color);
+    let scene = loader::load_from_str(&yaml, Path::new(""), true)?;
+    Ok(scene.objects[0].material().color)
+}
+
+#[test]
+fn colors_are_srgb_integers_or_linear_numbers() {
+    assert_eq!(load_sphere_color("[255, 0, 0]").unwrap(), Texture::Solid(Color::new(1.0, 0.0, 0.0)));
+    assert_eq!(load_sphere_color("[1.0, 0.5, 0.0]").unwrap(), Texture::Solid(Color::new(1.0, 0.5, 0.0)));
+}
+
+#[test]
+fn color_mixing_integers_and_numbers_is_an_error() {
+    let e = load_sphere_color("[255, 0.5, 0]").unwrap_err();
+    assert_eq!(e.path, "objects[0].sphere.color");
+    assert!(matches!(e.kind, SceneErrorKind::InvalidValue(_)), "{:?}", e.kind);
+}
+
+#[test]
+fn color_number_out_of_range_is_an_error() {
+    let e = load_sphere_color("[1.0, 1.5, 0.0]").unwrap_err();
+    assert_eq!(e.path, "objects[0].sphere.color[1]");
+    let e = load_sphere_color("[1.0, 0.5, -0.1]").unwrap_err();
+    assert_eq!(e.path, "objects[0].sphere.color[2]");
+}
+
+#[test]
+fn plane_with_zero_normal_is_an_error() {
+    let yaml = "\
+lights: []
+objects:
+  - plane: { point: [0.0, -1.0, 0.0], normal: [0.0, 0.0, 0.0] }
+";
+    let e = load_error(yaml, true);
+    assert_eq!(e.path, "objects[0].plane.normal");
+    assert_eq!(e.kind, SceneErrorKind::InvalidValue("expected non-zero vector".to_string()));
+}
+
+/// Loads a mesh of a single triangle, whose first vertex is at (1, 0, 0), with the given rotation
+fn load_rotated_vertex(rotation: &str) -> Vector3<f64> {
+    let dir = std::env::temp_dir().join(format!("min-rt-loader-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("triangle.obj"), "v 1 0 0\nv 0 1 0\nv 0 0 1\nf 1 2 3\n").unwrap();
+    let yaml = format!("\
+lights: []
+objects:
+  - mesh: {{ file: triangle.obj, position: [0.0, 0.0, 0.0], rotation: {} }}
+", rotation);
+    let scene = loader::load_from_str(&yaml, &dir, true).unwrap();
+    scene.get_object::<Mesh>(0).expect("mesh").triangles[0].vertices[0]
+}
+
+#[test]
+fn mesh_rotation_is_in_degrees() {
+    let v = load_rotated_vertex("[0.0, 30.0, 0.0]");
+    assert!((v - Vector3::new(30_f64.to_radians().cos(), 0.0, -30_f64.to_radians().sin())).magnitude() < 1e-9, "{:?}", v);
+    let v = load_rotated_vertex("[0.0, 90.0, 0.0]");
+    assert!((v - Vector3::new(0.0, 0.0, -1.0)).magnitude() < 1e-9, "{:?}", v);
+    let v = load_rotated_vertex("[0.0, 0.0, 90.0]");
+    assert!((v - Vector3::new(0.0, 1.0, 0.0)).magnitude() < 1e-9, "{:?}", v);
+}
+
+#[test]
+fn sample_counts_below_1_are_errors() {
+    let e = load_error("specs: { samples_per_pixel: 0 }\nlights: []\n", false);
+    assert_eq!(e.path, "specs.samples_per_pixel");
+    assert_eq!(e.kind, SceneErrorKind::InvalidValue("expected integer of at least 1".to_string()));
+    let e = load_error("specs: { light_samples: -3 }\nlights: []\n", false);
+    assert_eq!(e.path, "specs.light_samples");
+}
+
+#[test]
+fn omitted_lights_and_objects_are_empty() {
+    let scene = loader::load_from_str("specs: { samples_per_pixel: 4 }\n", Path::new(""), true).unwrap();
+    assert!(scene.lights.is_empty());
+    assert!(scene.objects.is_empty());
+}
+
+#[test]
+fn top_level_must_be_mapping() {
+    let e = load_error("just a string\n", false);
+    assert_eq!(e.path, "");
+    assert_eq!(e.kind, SceneErrorKind::WrongType { expected: "mapping".to_string(), found: "string".to_string() });
+    assert_eq!(e.to_string().split(" (").next(), Some("expected mapping, found string"));
+}
+
+#[test]
+fn surface_colors_are_within_0_and_1_but_light_colors_may_be_hdr() {
+    assert!(load_sphere_color("[2.0, 0.5, 0.0]").is_err());
+    let yaml = "\
+specs: { background_color: [0.0, 0.0, 2.5] }
+lights:
+  - ambient: { intensity: 1.0, color: [3.0, 1.0, 0.5] }
+";
+    let scene = loader::load_from_str(yaml, Path::new(""), true).unwrap();
+    assert_eq!(scene.specs.background_color, Color::new(0.0, 0.0, 2.5));
+    let e = load_error("lights:\n  - ambient: { intensity: 1.0, color: [-1.0, 1.0, 0.5] }\n", true);
+    assert_eq!(e.path, "lights[0].ambient.color[0]");
+    assert_eq!(e.kind, SceneErrorKind::InvalidValue("expected number of at least 0.0".to_string()));
+}
+
+#[test]
+fn spot_light_with_zero_direction_is_an_error() {
+    let yaml = "\
+lights:
+  - spot: { intensity: 1.0, position: [0.0, 5.0, 0.0], direction: [0.0, 0.0, 0.0], outer_angle: 20.0 }
+";
+    let e = load_error(yaml, true);
+    assert_eq!(e.path, "lights[0].spot.direction");
+    assert_eq!(e.kind, SceneErrorKind::InvalidValue("expected non-zero vector".to_string()));
+}
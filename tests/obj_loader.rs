@@ -0,0 +1,74 @@
+use min_rt::cgmath::Vector3;
+use min_rt::scene::obj_loader;
+
+const SQUARE_VERTICES: &str = "\
+v 0.0 0.0 0.0
+v 1.0 0.0 0.0
+v 1.0 1.0 0.0
+v 0.0 1.0 0.0
+";
+
+#[test]
+fn quad_is_triangulated_as_fan() {
+    let triangles = obj_loader::parse(&format!("{}f 1 2 3 4\n", SQUARE_VERTICES)).unwrap();
+    assert_eq!(triangles.len(), 2);
+    assert_eq!(triangles[0].vertices, [
+        Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 0.0)]);
+    assert_eq!(triangles[1].vertices, [
+        Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 0.0), Vector3::new(0.0, 1.0, 0.0)]);
+}
+
+#[test]
+fn ngon_is_triangulated_as_fan() {
+    let string = "\
+v 0.0 0.0 0.0
+v 1.0 0.0 0.0
+v 2.0 1.0 0.0
+v 1.0 2.0 0.0
+v 0.0 1.0 0.0
+f 1 2 3 4 5
+";
+    let triangles = obj_loader::parse(string).unwrap();
+    assert_eq!(triangles.len(), 3);
+    for (i, triangle) in triangles.iter().enumerate() {
+        assert_eq!(triangle.vertices[0], Vector3::new(0.0, 0.0, 0.0), "triangle {}", i);
+    }
+    assert_eq!(triangles[2].vertices[1], Vector3::new(1.0, 2.0, 0.0));
+    assert_eq!(triangles[2].vertices[2], Vector3::new(0.0, 1.0, 0.0));
+}
+
+#[test]
+fn negative_indices_are_relative_to_end() {
+    let relative = obj_loader::parse(&format!("{}f -4 -3 -2\n", SQUARE_VERTICES)).unwrap();
+    let absolute = obj_loader::parse(&format!("{}f 1 2 3\n", SQUARE_VERTICES)).unwrap();
+    assert_eq!(relative.len(), 1);
+    assert_eq!(relative[0].vertices, absolute[0].vertices);
+}
+
+#[test]
+fn normals_are_read_from_corners() {
+    let normals = "vn 0.0 0.0 2.0\nvn 0.0 1.0 0.0\n";
+    for face in ["f 1//1 2//1 3//2\n", "f 1/7/1 2/8/1 3/9/2\n"] {
+        let triangles = obj_loader::parse(&format!("{}{}{}", SQUARE_VERTICES, normals, face)).unwrap();
+        assert_eq!(triangles.len(), 1, "{}", face);
+        // Normalized
+        assert_eq!(triangles[0].normals, [
+            Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 1.0, 0.0)], "{}", face);
+    }
+}
+
+#[test]
+fn out_of_range_indices_are_errors() {
+    let faces = [
+        "f 1 2 5",      // vertex past the end
+        "f 0 1 2",      // indices are 1-based
+        "f -5 1 2",     // relative index before the start
+        "f 1//1 2 3",   // no normals
+        "f 1 2",        // too few corners
+    ];
+    for face in faces {
+        let result = obj_loader::parse(&format!("{}{}\n", SQUARE_VERTICES, face));
+        let message = result.err().unwrap_or_else(|| panic!("expected error for '{}'", face));
+        assert!(message.starts_with("line 5:"), "{}: {}", face, message);
+    }
+}
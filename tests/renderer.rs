@@ -0,0 +1,65 @@
+use std::sync::{Arc, RwLock};
+use min_rt::base::color::Color;
+use min_rt::canvas::canvas::Canvas;
+use min_rt::canvas::float_canvas::FloatCanvas;
+use min_rt::cgmath::Vector3;
+use min_rt::scene::renderer;
+use min_rt::scene::moving::Moving;
+use min_rt::scene::scene::{Light, Plane, Scene, Specs, Sphere};
+use min_rt::scene::shape::Material;
+
+/// Scene with a single sphere in front of the camera, against a blue background,
+/// lit only by white ambient light (so its shaded color is its own color)
+fn make_scene(material: Material) -> Scene {
+    let mut specs = Specs::new_with_defaults();
+    specs.background_color = Color::new(0.0, 0.0, 1.0);
+    let lights = vec![Light::Ambient { intensity: 1.0, color: Color::new(1.0, 1.0, 1.0) }];
+    let sphere = Sphere { center: Vector3::new(0.0, 0.0, 3.0), radius: 1.0, material };
+    Scene { specs, lights, objects: vec![Box::new(sphere)] }
+}
+
+/// Radiance at the center of the image
+fn render_center(scene: Scene) -> Color {
+    let scene = Arc::new(RwLock::new(scene));
+    let mut canvas = FloatCanvas::new(3, 3);
+    renderer::render_to_float_canvas(&scene, &mut canvas);
+    canvas.get_value(1, 1)
+}
+
+fn assert_color_eq(actual: Color, expected: Color) {
+    let difference = (actual.r - expected.r).abs() + (actual.g - expected.g).abs() + (actual.b - expected.b).abs();
+    assert!(difference < 1e-9, "expected {:?}, got {:?}", expected, actual);
+}
+
+#[test]
+fn transparent_sphere_is_blended_with_background_once() {
+    let material = Material {
+        color: Color::new(1.0, 0.0, 0.0).into(),
+        transparency: 0.5,
+        ..Material::new_with_defaults()
+    };
+    // The sphere's far side only lets the ray back out, so doesn't get shaded a second time
+    assert_color_eq(render_center(make_scene(material)), Color::new(0.5, 0.0, 0.5));
+}
+
+#[test]
+fn shutter_outside_frame_is_clamped() {
+    let material = Material { color: Color::new(1.0, 0.0, 0.0).into(), ..Material::new_with_defaults() };
+    let mut scene = make_scene(material);
+    // Starts off to the side, and reaches the center of the view at the end of the frame
+    let sphere = Sphere { center: Vector3::new(-10.0, 0.0, 3.0), radius: 1.0, material };
+    scene.objects = vec![Box::new(Moving::new(Box::new(sphere), Vector3::new(10.0, 0.0, 0.0)))];
+    scene.specs.shutter_open = 3.0;
+    scene.specs.shutter_close = 5.0;
+    assert_color_eq(render_center(scene), Color::new(1.0, 0.0, 0.0));
+}
+
+#[test]
+fn red_wall_is_seen_through_two_clear_spheres() {
+    let clear = Material { transparency: 1.0, ior: 1.0, ..Material::new_with_defaults() };
+    let mut scene = make_scene(clear);
+    scene.objects.push(Box::new(Sphere { center: Vector3::new(0.0, 0.0, 6.0), radius: 1.0, material: clear }));
+    let red = Material { color: Color::new(1.0, 0.0, 0.0).into(), ..Material::new_with_defaults() };
+    scene.objects.push(Box::new(Plane { point: Vector3::new(0.0, 0.0, 10.0), normal: Vector3::new(0.0, 0.0, -1.0), material: red }));
+    assert_color_eq(render_center(scene), Color::new(1.0, 0.0, 0.0));
+}
@@ -0,0 +1,214 @@
+use std::path::Path;
+use min_rt::base::color::{Color, OutputTransfer};
+use min_rt::base::tone_mapping::ToneMapping;
+use min_rt::cgmath::{Quaternion, Vector3};
+use min_rt::scene::camera::{Camera, Projection, Stereo, StereoLayout};
+use min_rt::scene::loader;
+use min_rt::scene::mesh::Mesh;
+use min_rt::scene::moving::Moving;
+use min_rt::scene::saver;
+use min_rt::scene::scene::{Attenuation, Light, Plane, SamplePattern, Scene, Specs, Sphere};
+use min_rt::scene::shape::{Material, Shape};
+use min_rt::scene::texture::{Pattern, PatternKind, Texture, TextureSpace};
+
+fn make_scene() -> Scene {
+    let specs = Specs {
+        canvas_width: 80.0,
+        canvas_height: 60.0,
+        viewport_width: 1.3333333333333333,
+        viewport_height: 1.0,
+        viewport_distance: 0.75,
+        pixel_ar: 0.5,
+        camera_pos: Vector3::new(0.1, -2.5, 1e-7),
+        camera_orientation: Quaternion::new(0.9238795325112867, 0.0, 0.3826834323650898, 0.0),
+        projection: Projection::Orthographic { height: 4.5 },
+        stereo: Some(Stereo { layout: StereoLayout::TopBottom, eye_separation: 0.065, convergence: 4.0 }),
+        camera: Some(Camera {
+            position: Vector3::new(1.0, 2.0, -3.0),
+            look_at: Vector3::new(0.0, 0.0, 3.0),
+            up: Vector3::new(0.0, 1.0, 0.0),
+            fov: 45.0,
+            aperture: 0.05,
+            focus_distance: 5.5,
+        }),
+        background_color: Color::from_u8(12, 34, 56),
+        samples_per_pixel: 9,
+        sample_pattern: SamplePattern::Jittered,
+        light_samples: 25,
+        shutter_open: 0.25,
+        shutter_close: 0.75,
+        camera_velocity: Vector3::new(0.5, 0.0, 0.0),
+        tone_mapping: ToneMapping::AcesFilmic,
+        exposure: -0.5,
+        output_transfer: OutputTransfer::Linear,
+    };
+    let lights = vec![
+        Light::Ambient { intensity: 0.2, color: Color::new(0.5, 0.6, 1.0) },
+        Light::Point {
+            intensity: 0.6,
+            color: Color::from_srgb_u8(255, 214, 170),
+            position: Vector3::new(2.0, 1.0, 0.0),
+            attenuation: Attenuation::InverseSquare,
+            range: Some(20.0),
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+        },
+        Light::Point {
+            intensity: 2.0,
+            color: Color::new(1.0, 1.0, 1.0),
+            position: Vector3::new(-2.0, 1.0, 0.0),
+            attenuation: Attenuation::Coefficients { constant: 1.0, linear: 0.09, quadratic: 0.032 },
+            range: None,
+            velocity: Vector3::new(0.25, 0.0, -0.5),
+        },
+        Light::Directional { intensity: 1.0 / 3.0, color: Color::new(1.0, 1.0, 1.0), direction: Vector3::new(1.0, 4.0, -4.0) },
+        Light::Spot {
+            intensity: 0.9,
+            color: Color::new(1.0, 0.9, 0.8),
+            position: Vector3::new(0.0, 5.0, 3.0),
+            direction: Vector3::new(0.0, -1.0, 0.0),
+            inner_angle: 15.0,
+            outer_angle: 22.5,
+            attenuation: Attenuation::None,
+            range: None,
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+        },
+        Light::Rect {
+            intensity: 0.5,
+            color: Color::new(1.0, 1.0, 1.0),
+            position: Vector3::new(0.0, 4.0, 3.0),
+            edge_u: Vector3::new(2.0, 0.0, 0.0),
+            edge_v: Vector3::new(0.0, 0.0, 1.5),
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+        },
+        Light::Sphere {
+            intensity: 0.25,
+            color: Color::new(0.2, 0.4, 1.0),
+            position: Vector3::new(-3.0, 1.0, 0.0),
+            radius: 0.5,
+            velocity: Vector3::new(0.0, 1.0, 0.0),
+        },
+    ];
+    let objects: Vec<Box<dyn Shape>> = vec![
+        Box::new(Sphere {
+            center: Vector3::new(0.0, 0.0, 3.0),
+            radius: 1.0,
+            material: Material {
+                color: Color::new(0.1, 0.2, 0.3).into(),
+                specular: 500.0,
+                reflective: 0.2,
+                transparency: 0.0,
+                ior: 1.0,
+            },
+        }),
+        Box::new(Sphere {
+            center: Vector3::new(-2.0, 0.5, 4.0),
+            radius: 0.7,
+            material: Material {
+                color: Color::from_u8(255, 128, 0).into(),
+                specular: -1.0,
+                reflective: 0.0,
+                transparency: 0.9,
+                ior: 1.52,
+            },
+        }),
+        Box::new(Moving::new(
+            Box::new(Sphere {
+                center: Vector3::new(1.0, 1.0, 5.0),
+                radius: 0.5,
+                material: Material {
+                    color: Texture::Pattern(Pattern {
+                        kind: PatternKind::Wood { turbulence: 0.35 },
+                        colors: [Color::new(0.6, 0.3, 0.1), Color::new(0.3, 0.15, 0.05)],
+                        scale: 0.1,
+                        space: TextureSpace::Object,
+                    }),
+                    ..Material::new_with_defaults()
+                },
+            }),
+            Vector3::new(0.0, -0.2, 0.1),
+        )),
+        Box::new(Plane {
+            point: Vector3::new(0.0, -1.0, 0.0),
+            normal: Vector3::new(0.0, 1.0, 0.0),
+            material: Material {
+                color: Texture::Pattern(Pattern {
+                    kind: PatternKind::Checker,
+                    colors: [Color::new(1.0, 1.0, 1.0), Color::from_u8(20, 20, 20)],
+                    scale: 0.5,
+                    space: TextureSpace::Uv,
+                }),
+                ..Material::new_with_defaults()
+            },
+        }),
+    ];
+    Scene { objects, lights, specs }
+}
+
+fn assert_same_objects(a: &Scene, b: &Scene) {
+    assert_eq!(a.objects.len(), b.objects.len());
+    for i in 0..a.objects.len() {
+        if let Some(moving) = a.get_object::<Moving>(i) {
+            let other = b.get_object::<Moving>(i).expect("moving");
+            assert_eq!(moving.velocity, other.velocity, "objects[{}]", i);
+            assert_eq!(moving.get_shape::<Sphere>(), other.get_shape::<Sphere>(), "objects[{}]", i);
+        } else if let Some(sphere) = a.get_object::<Sphere>(i) {
+            assert_eq!(Some(sphere), b.get_object::<Sphere>(i), "objects[{}]", i);
+        } else if let Some(plane) = a.get_object::<Plane>(i) {
+            assert_eq!(Some(plane), b.get_object::<Plane>(i), "objects[{}]", i);
+        } else if let Some(mesh) = a.get_object::<Mesh>(i) {
+            let other = b.get_object::<Mesh>(i).expect("mesh");
+            assert_eq!(mesh.source, other.source, "objects[{}]", i);
+            assert_eq!(mesh.material, other.material, "objects[{}]", i);
+            assert_eq!(mesh.triangles.len(), other.triangles.len(), "objects[{}]", i);
+        } else {
+            panic!("objects[{}]: unexpected type", i);
+        }
+    }
+}
+
+#[test]
+fn round_trip_reproduces_scene() {
+    let scene = make_scene();
+    let yaml = saver::to_yaml(&scene).unwrap();
+    let loaded = loader::load_from_str(&yaml, Path::new("."), true).unwrap();
+
+    assert_eq!(scene.specs, loaded.specs);
+    assert_eq!(scene.lights, loaded.lights);
+    assert_same_objects(&scene, &loaded);
+
+    // Saving again gives identical output
+    assert_eq!(yaml, saver::to_yaml(&loaded).unwrap());
+}
+
+#[test]
+fn round_trip_reproduces_scene_files() {
+    for filepath in ["scene1.yaml", "scene-mesh.yaml"] {
+        let scene = loader::load_strict(filepath).unwrap();
+        let yaml = saver::to_yaml(&scene).unwrap();
+        let loaded = loader::load_from_str(&yaml, Path::new("."), true).unwrap();
+
+        assert_eq!(scene.specs, loaded.specs, "{}", filepath);
+        assert_eq!(scene.lights, loaded.lights, "{}", filepath);
+        assert_same_objects(&scene, &loaded);
+    }
+}
+
+#[test]
+fn round_trip_keeps_hdr_light_and_background_colors() {
+    let mut scene = make_scene();
+    scene.specs.background_color = Color::new(0.0, 1.5, 3.0);
+    scene.lights[0] = Light::Ambient { intensity: 0.2, color: Color::new(4.0, 2.0, 1.0) };
+    let yaml = saver::to_yaml(&scene).unwrap();
+    let loaded = loader::load_from_str(&yaml, Path::new("."), true).unwrap();
+
+    assert_eq!(scene.specs.background_color, loaded.specs.background_color);
+    assert_eq!(scene.lights, loaded.lights);
+}
+
+#[test]
+fn unsaveable_mesh_is_an_error() {
+    let mut scene = make_scene();
+    scene.objects.push(Box::new(Mesh::new(vec![], Material::new_with_defaults())));
+    let error = saver::to_yaml(&scene).unwrap_err();
+    assert_eq!(error.path, "objects[4]");
+}